@@ -0,0 +1,645 @@
+//! Admin CLI for running a presale end to end: create the sale token,
+//! create and configure a presale, deposit sale tokens, inspect on-chain
+//! state and move the sale through its phases.
+//!
+//! The RPC URL and fee-payer keypair come from the Solana CLI config file
+//! unless overridden with `--url` and `--keypair`. With `--dry-run`, every
+//! transaction is simulated and its logs printed instead of being sent.
+
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use anyhow::{anyhow, bail, Context, Result};
+use base64::Engine;
+use clap::{ArgGroup, Parser, Subcommand};
+use presale_client::{InitializePresaleParams, VestingSchedule};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::account::from_account;
+use solana_sdk::clock::Clock;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{read_keypair_file, Keypair, Signer};
+use solana_sdk::sysvar;
+use solana_sdk::transaction::Transaction;
+use token_client::{InitializeTokenParams, TransferFeeArgs};
+
+#[derive(Parser)]
+#[command(name = "presale-cli", version, about = "Create and operate token presales")]
+struct Cli {
+    /// Solana CLI config file to read the RPC URL and keypair from
+    #[arg(long, short = 'C', global = true)]
+    config: Option<String>,
+
+    /// RPC URL, overriding the config file
+    #[arg(long, short = 'u', global = true)]
+    url: Option<String>,
+
+    /// Fee payer and authority keypair, overriding the config file
+    #[arg(long, short = 'k', global = true)]
+    keypair: Option<String>,
+
+    /// Simulate transactions and print their logs instead of sending them
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create the sale token through the token program's `initialize_token`
+    CreateToken {
+        #[arg(long)]
+        name: String,
+        #[arg(long)]
+        symbol: String,
+        #[arg(long)]
+        uri: String,
+        #[arg(long, default_value_t = 9)]
+        decimals: u8,
+        /// Initial supply in base units, minted to the authority's token account
+        #[arg(long)]
+        supply: u64,
+        /// Hard cap on the total supply in base units; requires --pda-mint-authority
+        #[arg(long, requires = "pda_mint_authority")]
+        max_supply: Option<u64>,
+        /// Transfer fee in basis points
+        #[arg(long, requires = "maximum_fee")]
+        transfer_fee_bps: Option<u16>,
+        /// Cap on the transfer fee in base units
+        #[arg(long, requires = "transfer_fee_bps")]
+        maximum_fee: Option<u64>,
+        /// Program to route every transfer through, e.g. the lockup transfer hook
+        #[arg(long)]
+        transfer_hook_program: Option<Pubkey>,
+        /// Freeze new token accounts until the freeze authority thaws them
+        #[arg(long)]
+        default_frozen: bool,
+        /// Freeze authority for the mint; defaults to the token authority
+        #[arg(long)]
+        freeze_authority: Option<Pubkey>,
+        /// Create the mint without a freeze authority
+        #[arg(long, conflicts_with_all = ["freeze_authority", "default_frozen"])]
+        no_freeze_authority: bool,
+        /// Hand the mint authority to the token program's PDA after the initial mint
+        #[arg(long)]
+        pda_mint_authority: bool,
+    },
+
+    /// Create a presale selling `mint`
+    CreatePresale {
+        #[arg(long)]
+        presale_id: u64,
+        #[arg(long)]
+        mint: Pubkey,
+        /// Destination of the raised SOL; defaults to the authority
+        #[arg(long)]
+        withdraw_wallet: Option<Pubkey>,
+        /// Minimum raise in lamports
+        #[arg(long)]
+        soft_cap: u64,
+        /// Maximum raise in lamports
+        #[arg(long)]
+        hard_cap: u64,
+        /// Lamports paid for `price_tokens` whole tokens
+        #[arg(long)]
+        price_lamports: u64,
+        /// Whole tokens received for `price_lamports`
+        #[arg(long)]
+        price_tokens: u64,
+        /// Unix timestamp contributions open at
+        #[arg(long)]
+        start_time: i64,
+        /// Unix timestamp contributions close at
+        #[arg(long)]
+        end_time: i64,
+        /// Minimum single contribution in lamports
+        #[arg(long)]
+        min_contribution: u64,
+        /// Maximum total contribution per wallet in lamports
+        #[arg(long)]
+        max_contribution: u64,
+        /// Hex-encoded allowlist Merkle root
+        #[arg(long, value_parser = parse_hash)]
+        merkle_root: Option<[u8; 32]>,
+        /// Share of tokens unlocked at TGE, in basis points
+        #[arg(long, default_value_t = 10_000)]
+        tge_unlock_bps: u16,
+        /// Seconds after TGE before linear vesting starts
+        #[arg(long, default_value_t = 0)]
+        cliff_duration: i64,
+        /// Seconds over which the remainder vests after the cliff
+        #[arg(long, default_value_t = 0)]
+        vesting_duration: i64,
+        /// Seconds after TGE during which claimed tokens cannot be transferred
+        #[arg(long, default_value_t = 0)]
+        lockup_duration: i64,
+    },
+
+    /// Set or clear a presale's allowlist Merkle root
+    SetMerkleRoot {
+        #[arg(long)]
+        presale_id: u64,
+        /// Hex-encoded root; omit to open the sale to everyone
+        #[arg(long, value_parser = parse_hash)]
+        merkle_root: Option<[u8; 32]>,
+    },
+
+    /// Escrow sale tokens from the authority's token account
+    DepositSaleTokens {
+        #[arg(long)]
+        presale_id: u64,
+        /// Amount in base units
+        #[arg(long)]
+        amount: u64,
+        /// Transfer hook program set on the mint, if any
+        #[arg(long)]
+        transfer_hook_program: Option<Pubkey>,
+    },
+
+    /// Return escrowed sale tokens: all of them once the presale failed or was
+    /// cancelled, otherwise only the surplus above what contributors are owed
+    WithdrawSaleTokens {
+        #[arg(long)]
+        presale_id: u64,
+        /// Amount in base units
+        #[arg(long)]
+        amount: u64,
+        /// Transfer hook program set on the mint, if any
+        #[arg(long)]
+        transfer_hook_program: Option<Pubkey>,
+    },
+    
+    /// Open claims once the presale has succeeded
+    EnableClaims {
+        #[arg(long)]
+        presale_id: u64,
+    },
+
+    /// Cancel the presale and open refunds
+    EnableRefunds {
+        #[arg(long)]
+        presale_id: u64,
+    },
+
+    /// Withdraw the raised SOL to the withdraw wallet
+    Finalize {
+        #[arg(long)]
+        presale_id: u64,
+    },
+
+    /// Print a presale's state
+    ShowPresale {
+        #[arg(long)]
+        presale_id: u64,
+        /// Authority that created the presale; defaults to the configured keypair
+        #[arg(long)]
+        authority: Option<Pubkey>,
+    },
+
+    /// Print a contributor's position, as computed by `get_contribution`
+    ShowContribution {
+        #[arg(long)]
+        presale_id: u64,
+        /// Authority that created the presale; defaults to the configured keypair
+        #[arg(long)]
+        authority: Option<Pubkey>,
+        #[arg(long)]
+        contributor: Pubkey,
+    },
+
+    /// Thaw a token account on a default-frozen mint, e.g. a contributor's account or a presale escrow
+    #[command(group(ArgGroup::new("target").required(true).args(["owner", "token_account", "presale_id"])))]
+    ThawAccount {
+        #[arg(long)]
+        token_state: Pubkey,
+        /// Wallet whose associated token account is thawed
+        #[arg(long)]
+        owner: Option<Pubkey>,
+        /// Token account to thaw
+        #[arg(long)]
+        token_account: Option<Pubkey>,
+        /// Presale whose sale-token escrow is thawed, to run before depositing
+        #[arg(long)]
+        presale_id: Option<u64>,
+        /// Authority that created the presale; defaults to the configured keypair
+        #[arg(long, requires = "presale_id")]
+        presale_authority: Option<Pubkey>,
+    },
+    
+    /// Print a token's state account
+    ShowToken {
+        #[arg(long)]
+        token_state: Pubkey,
+    },
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    let config = match &cli.config {
+        Some(path) => solana_cli_config::Config::load(path)
+            .with_context(|| format!("Failed to load config file {path}"))?,
+        None => solana_cli_config::CONFIG_FILE
+            .as_ref()
+            .and_then(|path| solana_cli_config::Config::load(path).ok())
+            .unwrap_or_default(),
+    };
+    let url = cli.url.clone().unwrap_or(config.json_rpc_url);
+    let keypair_path = cli.keypair.clone().unwrap_or(config.keypair_path);
+    let payer = read_keypair_file(&keypair_path)
+        .map_err(|err| anyhow!("Failed to read keypair {keypair_path}: {err}"))?;
+    let rpc = RpcClient::new_with_commitment(url, CommitmentConfig::confirmed());
+
+    let client = Client { rpc, payer, dry_run: cli.dry_run };
+    let authority = client.payer.pubkey();
+
+    match cli.command {
+        Command::CreateToken {
+            name,
+            symbol,
+            uri,
+            decimals,
+            supply,
+            max_supply,
+            transfer_fee_bps,
+            maximum_fee,
+            transfer_hook_program,
+            default_frozen,
+            freeze_authority,
+            no_freeze_authority,
+            pda_mint_authority,
+        } => {
+            let token_state = Keypair::new();
+            let mint = Keypair::new();
+            let transfer_fee = transfer_fee_bps.zip(maximum_fee).map(|(basis_points, maximum_fee)| TransferFeeArgs {
+                basis_points,
+                maximum_fee,
+            });
+
+            let ix = token_client::initialize_token(
+                &authority,
+                &token_state.pubkey(),
+                &mint.pubkey(),
+                InitializeTokenParams {
+                    name,
+                    symbol,
+                    uri,
+                    decimals,
+                    supply,
+                    additional_metadata: vec![],
+                    transfer_fee,
+                    transfer_hook_program_id: transfer_hook_program,
+                    default_account_frozen: default_frozen,
+                    freeze_authority: (!no_freeze_authority).then(|| freeze_authority.unwrap_or(authority)),
+                    pda_mint_authority,
+                    max_supply,
+                },
+            );
+            let mut instructions = vec![ix];
+            // Transfers of a hooked mint fail until the hook's extra-account-metas exist
+            if let Some(hook) = transfer_hook_program {
+                instructions.push(presale_client::initialize_extra_account_meta_list(
+                    &hook,
+                    &authority,
+                    &mint.pubkey(),
+                ));
+            }
+            client.process(&instructions, &[&token_state, &mint])?;
+
+            println!("Token state: {}", token_state.pubkey());
+            println!("Mint: {}", mint.pubkey());
+        }
+
+        Command::CreatePresale {
+            presale_id,
+            mint,
+            withdraw_wallet,
+            soft_cap,
+            hard_cap,
+            price_lamports,
+            price_tokens,
+            start_time,
+            end_time,
+            min_contribution,
+            max_contribution,
+            merkle_root,
+            tge_unlock_bps,
+            cliff_duration,
+            vesting_duration,
+            lockup_duration,
+        } => {
+            // The presale records whichever token program owns the mint
+            let token_program = client
+                .rpc
+                .get_account(&mint)
+                .with_context(|| format!("Failed to fetch mint {mint}"))?
+                .owner;
+
+            let ix = presale_client::initialize_presale(
+                &authority,
+                &withdraw_wallet.unwrap_or(authority),
+                &mint,
+                &token_program,
+                InitializePresaleParams {
+                    presale_id,
+                    soft_cap,
+                    hard_cap,
+                    price_lamports,
+                    price_tokens,
+                    start_time,
+                    end_time,
+                    min_contribution,
+                    max_contribution,
+                    merkle_root,
+                    vesting: VestingSchedule {
+                        tge_unlock_bps,
+                        cliff_duration,
+                        vesting_duration,
+                    },
+                    lockup_duration,
+                },
+            );
+            client.process(&[ix], &[])?;
+
+            println!("Presale state: {}", presale_client::presale_state_address(&authority, presale_id).0);
+        }
+
+        Command::SetMerkleRoot { presale_id, merkle_root } => {
+            let presale_state = presale_client::presale_state_address(&authority, presale_id).0;
+            let ix = presale_client::set_merkle_root(&presale_state, &authority, merkle_root);
+            client.process(&[ix], &[])?;
+        }
+
+        Command::DepositSaleTokens {
+            presale_id,
+            amount,
+            transfer_hook_program,
+        } => {
+            let presale_state = presale_client::presale_state_address(&authority, presale_id).0;
+            let state = client.fetch_presale(&presale_state)?;
+            let authority_token_account =
+                get_associated_token_address_with_program_id(&authority, &state.mint, &state.token_program);
+            let extra_accounts = transfer_hook_program
+                .map(|hook| presale_client::transfer_hook_accounts(&hook, &state.mint, &authority_token_account))
+                .unwrap_or_default();
+
+            let ix = presale_client::deposit_sale_tokens(
+                &presale_state,
+                &authority,
+                &authority_token_account,
+                &state.mint,
+                &state.token_program,
+                amount,
+                &extra_accounts,
+            );
+            client.process(&[ix], &[])?;
+        }
+
+        Command::WithdrawSaleTokens {
+            presale_id,
+            amount,
+            transfer_hook_program,
+        } => {
+            let presale_state = presale_client::presale_state_address(&authority, presale_id).0;
+            let state = client.fetch_presale(&presale_state)?;
+            let authority_token_account =
+                get_associated_token_address_with_program_id(&authority, &state.mint, &state.token_program);
+            let (treasury_token_account, _) = presale_client::treasury_token_account_address(&presale_state);
+            let extra_accounts = transfer_hook_program
+                .map(|hook| presale_client::transfer_hook_accounts(&hook, &state.mint, &treasury_token_account))
+                .unwrap_or_default();
+            
+            let ix = presale_client::withdraw_sale_tokens(
+                &presale_state,
+                &authority,
+                &authority_token_account,
+                &state.mint,
+                &state.token_program,
+                amount,
+                &extra_accounts,
+            );
+            client.process(&[ix], &[])?;
+        }
+        
+        Command::EnableClaims { presale_id } => {
+            let presale_state = presale_client::presale_state_address(&authority, presale_id).0;
+            let ix = presale_client::enable_claims(&presale_state, &authority);
+            client.process(&[ix], &[])?;
+        }
+
+        Command::EnableRefunds { presale_id } => {
+            let presale_state = presale_client::presale_state_address(&authority, presale_id).0;
+            let ix = presale_client::enable_refunds(&presale_state, &authority);
+            client.process(&[ix], &[])?;
+        }
+
+        Command::Finalize { presale_id } => {
+            let presale_state = presale_client::presale_state_address(&authority, presale_id).0;
+            let state = client.fetch_presale(&presale_state)?;
+            let ix = presale_client::finalize_presale(&presale_state, &authority, &state.withdraw_wallet);
+            client.process(&[ix], &[])?;
+        }
+
+        Command::ShowPresale {
+            presale_id,
+            authority: presale_authority,
+        } => {
+            let presale_authority = presale_authority.unwrap_or(authority);
+            let presale_state = presale_client::presale_state_address(&presale_authority, presale_id).0;
+            let mut state = client.fetch_presale(&presale_state)?;
+            let stored_status = state.status;
+            
+            // The program only persists time-based transitions on the next write,
+            // so resolve them the same way it would right now
+            let clock_account = client.rpc.get_account(&sysvar::clock::ID)?;
+            let clock: Clock = from_account(&clock_account).context("Failed to decode the clock sysvar")?;
+            state.refresh_status(clock.unix_timestamp)?;
+            
+            println!("Presale state:       {presale_state}");
+            println!("Presale id:          {}", state.presale_id);
+            println!("Authority:           {}", state.authority);
+            if state.status == stored_status {
+                println!("Status:              {:?}", state.status);
+            } else {
+                println!("Status:              {:?} (stored as {:?})", state.status, stored_status);
+            }
+            println!("Mint:                {}", state.mint);
+            println!("Token program:       {}", state.token_program);
+            println!("Window:              {} - {}", state.start_time, state.end_time);
+            println!("Soft / hard cap:     {} / {} lamports", state.soft_cap, state.hard_cap);
+            println!("Price:               {} lamports per {} tokens", state.price_lamports, state.price_tokens);
+            println!("Contribution limits: {} - {} lamports", state.min_contribution, state.max_contribution);
+            println!("Raised:              {} lamports", state.total_contributions);
+            println!("Refunded:            {} lamports", state.total_refunded);
+            println!("Tokens deposited:    {}", state.tokens_deposited);
+            println!("Tokens claimed:      {}", state.tokens_claimed);
+            println!("Treasury:            {}", state.treasury);
+            println!("Withdraw wallet:     {}", state.withdraw_wallet);
+            println!(
+                "Merkle root:         {}",
+                state.merkle_root.map(|root| to_hex(&root)).unwrap_or_else(|| "none".to_string())
+            );
+            println!(
+                "Vesting:             {} bps at TGE, {}s cliff, {}s linear",
+                state.vesting.tge_unlock_bps, state.vesting.cliff_duration, state.vesting.vesting_duration
+            );
+            println!(
+                "Claims start:        {}",
+                state.claims_start.map(|t| t.to_string()).unwrap_or_else(|| "not enabled".to_string())
+            );
+            println!("Lockup duration:     {}s", state.lockup_duration);
+        }
+
+        Command::ShowContribution {
+            presale_id,
+            authority: presale_authority,
+            contributor,
+        } => {
+            let presale_authority = presale_authority.unwrap_or(authority);
+            let presale_state = presale_client::presale_state_address(&presale_authority, presale_id).0;
+            let ix = presale_client::get_contribution(&presale_state, &contributor);
+            let info = client.simulate_contribution(ix)?;
+
+            println!("Contributor:       {}", info.contributor);
+            println!("Status:            {:?}", info.status);
+            println!("Contributed:       {} lamports", info.contributed_lamports);
+            println!("Tokens entitled:   {}", info.tokens_entitled);
+            println!("Vested:            {}", info.vested_amount);
+            println!("Claimed:           {}", info.claimed_amount);
+            println!("Claimable now:     {}", info.claimable_amount);
+            println!("Refunded:          {}", info.refunded);
+            println!("Refund eligible:   {}", info.refund_eligible);
+        }
+
+        Command::ThawAccount {
+            token_state,
+            owner,
+            token_account,
+            presale_id,
+            presale_authority,
+        } => {
+            let state = client.fetch_token_state(&token_state)?;
+            let token_account = match (owner, token_account, presale_id) {
+                (Some(owner), _, _) => token_client::associated_token_address(&owner, &state.mint),
+                (_, Some(token_account), _) => token_account,
+                (_, _, Some(presale_id)) => {
+                    let presale_authority = presale_authority.unwrap_or(authority);
+                    let presale_state = presale_client::presale_state_address(&presale_authority, presale_id).0;
+                    presale_client::treasury_token_account_address(&presale_state).0
+                }
+                _ => bail!("Pass --owner, --token-account or --presale-id"),
+            };
+            let ix = token_client::thaw_account(&authority, &token_state, &state.mint, &token_account);
+            client.process(&[ix], &[])?;
+            
+            println!("Thawed {token_account}");
+        }
+        
+        Command::ShowToken { token_state } => {
+            let state = client.fetch_token_state(&token_state)?;
+
+            println!("Token state:       {token_state}");
+            println!("Mint:              {}", state.mint);
+            println!("Authority:         {}", state.authority);
+            println!("Pending authority: {}", display_optional(state.pending_authority));
+            println!("Mint authority:    {}", display_optional(state.mint_authority));
+            println!("Freeze authority:  {}", display_optional(state.freeze_authority));
+            println!("Decimals:          {}", state.decimals);
+            println!("Total supply:      {}", state.total_supply);
+            println!("Max supply:        {}", display_optional(state.max_supply));
+        }
+    }
+
+    Ok(())
+}
+
+struct Client {
+    rpc: RpcClient,
+    payer: Keypair,
+    dry_run: bool,
+}
+
+impl Client {
+    /// Signs `instructions` with the payer and `signers`, then sends them, or
+    /// only simulates them in dry-run mode.
+    fn process(&self, instructions: &[Instruction], signers: &[&Keypair]) -> Result<()> {
+        let mut all_signers = vec![&self.payer];
+        all_signers.extend_from_slice(signers);
+
+        let blockhash = self.rpc.get_latest_blockhash()?;
+        let tx = Transaction::new_signed_with_payer(instructions, Some(&self.payer.pubkey()), &all_signers, blockhash);
+
+        if self.dry_run {
+            let result = self.rpc.simulate_transaction(&tx)?.value;
+            for log in result.logs.unwrap_or_default() {
+                println!("  {log}");
+            }
+            if let Some(err) = result.err {
+                bail!("Simulation failed: {err}");
+            }
+            println!("Simulation succeeded, {} compute units", result.units_consumed.unwrap_or_default());
+        } else {
+            let signature = self.rpc.send_and_confirm_transaction_with_spinner(&tx)?;
+            println!("Signature: {signature}");
+        }
+
+        Ok(())
+    }
+
+    fn fetch_presale(&self, presale_state: &Pubkey) -> Result<presale_client::PresaleState> {
+        let data = self
+            .rpc
+            .get_account_data(presale_state)
+            .with_context(|| format!("Failed to fetch presale state {presale_state}"))?;
+        Ok(presale_client::decode_presale_state(&data)?)
+    }
+
+    fn fetch_token_state(&self, token_state: &Pubkey) -> Result<token_client::TokenState> {
+        let data = self
+            .rpc
+            .get_account_data(token_state)
+            .with_context(|| format!("Failed to fetch token state {token_state}"))?;
+        Ok(token_client::decode_token_state(&data)?)
+    }
+    
+    /// `get_contribution` only reports through return data, so it is always
+    /// simulated regardless of `--dry-run`.
+    fn simulate_contribution(&self, ix: Instruction) -> Result<presale_client::ContributionInfo> {
+        let blockhash = self.rpc.get_latest_blockhash()?;
+        let tx = Transaction::new_signed_with_payer(&[ix], Some(&self.payer.pubkey()), &[&self.payer], blockhash);
+        let result = self.rpc.simulate_transaction(&tx)?.value;
+
+        if let Some(err) = result.err {
+            for log in result.logs.unwrap_or_default() {
+                println!("  {log}");
+            }
+            bail!("Simulation failed: {err}");
+        }
+
+        let return_data = result.return_data.context("get_contribution returned no data")?;
+        let data = base64::engine::general_purpose::STANDARD.decode(return_data.data.0)?;
+        Ok(presale_client::decode_contribution_info(&data)?)
+    }
+}
+
+fn parse_hash(value: &str) -> Result<[u8; 32]> {
+    let value = value.trim_start_matches("0x");
+    if value.len() != 64 || !value.is_ascii() {
+        bail!("Expected 32 hex-encoded bytes");
+    }
+
+    let mut hash = [0u8; 32];
+    for (i, byte) in hash.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&value[2 * i..2 * i + 2], 16)?;
+    }
+    Ok(hash)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn display_optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_else(|| "none".to_string())
+}
@@ -0,0 +1,402 @@
+//! Off-chain client for the presale program: PDA derivation, instruction
+//! builders for every entrypoint and account decoders.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::system_program;
+use anchor_lang::{AccountDeserialize, InstructionData, ToAccountMetas};
+use presale::{accounts, instruction};
+
+pub use presale::{
+    ContributionInfo, LockupRecord, PresaleState, PresaleStatus, UserContribution, VestingSchedule,
+    ID as PROGRAM_ID,
+};
+
+/// Presale state PDA, seeded by the creating authority and the presale id.
+pub fn presale_state_address(authority: &Pubkey, presale_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"presale_state", authority.as_ref(), presale_id.to_le_bytes().as_ref()],
+        &PROGRAM_ID,
+    )
+}
+
+/// Treasury PDA holding the SOL raised by a presale.
+pub fn treasury_address(presale_state: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"treasury", presale_state.as_ref()], &PROGRAM_ID)
+}
+
+/// Token account escrowing a presale's sale tokens.
+pub fn treasury_token_account_address(presale_state: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"treasury_token_account", presale_state.as_ref()], &PROGRAM_ID)
+}
+
+/// A contributor's position in a presale.
+pub fn user_contribution_address(presale_state: &Pubkey, contributor: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"user_contribution", presale_state.as_ref(), contributor.as_ref()],
+        &PROGRAM_ID,
+    )
+}
+
+/// A token account's transfer lockup, holding one tranche per presale of its mint.
+pub fn lockup_address(mint: &Pubkey, token_account: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"lockup", mint.as_ref(), token_account.as_ref()], &PROGRAM_ID)
+}
+
+/// The lockup hook's extra-account-metas PDA for `mint`.
+pub fn extra_account_meta_list_address(hook_program_id: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"extra-account-metas", mint.as_ref()], hook_program_id)
+}
+
+/// Remaining accounts Token-2022 needs to run the lockup transfer hook when
+/// tokens of `mint` move out of `source_token_account`: the hook's extra
+/// accounts, the hook program and its extra-account-metas PDA.
+///
+/// Every instruction that moves tokens of a hooked mint takes them after its
+/// own accounts: the presale's `deposit_sale_tokens`, `withdraw_sale_tokens`
+/// and `claim_tokens` (as `extra_accounts` here), and the token program's
+/// `transfer_tokens`.
+pub fn transfer_hook_accounts(
+    hook_program_id: &Pubkey,
+    mint: &Pubkey,
+    source_token_account: &Pubkey,
+) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new_readonly(PROGRAM_ID, false),
+        AccountMeta::new_readonly(lockup_address(mint, source_token_account).0, false),
+        AccountMeta::new_readonly(*hook_program_id, false),
+        AccountMeta::new_readonly(extra_account_meta_list_address(hook_program_id, mint).0, false),
+    ]
+}
+
+/// Creates the lockup hook's extra-account-metas PDA for a hooked `mint`.
+/// Send it right after the token program's `initialize_token`: Token-2022
+/// fails every transfer of the mint until this account exists.
+pub fn initialize_extra_account_meta_list(hook_program_id: &Pubkey, payer: &Pubkey, mint: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: *hook_program_id,
+        accounts: transfer_hook::accounts::InitializeExtraAccountMetaList {
+            payer: *payer,
+            extra_account_meta_list: extra_account_meta_list_address(hook_program_id, mint).0,
+            mint: *mint,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: transfer_hook::instruction::InitializeExtraAccountMetaList {}.data(),
+    }
+}
+
+/// Sale parameters for `initialize_presale`.
+#[derive(Clone)]
+pub struct InitializePresaleParams {
+    pub presale_id: u64,
+    pub soft_cap: u64,
+    pub hard_cap: u64,
+    pub price_lamports: u64,
+    pub price_tokens: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub min_contribution: u64,
+    pub max_contribution: u64,
+    pub merkle_root: Option<[u8; 32]>,
+    pub vesting: VestingSchedule,
+    pub lockup_duration: i64,
+}
+
+pub fn initialize_presale(
+    authority: &Pubkey,
+    withdraw_wallet: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    params: InitializePresaleParams,
+) -> Instruction {
+    let presale_state = presale_state_address(authority, params.presale_id).0;
+
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::InitializePresale {
+            authority: *authority,
+            presale_state,
+            treasury: treasury_address(&presale_state).0,
+            withdraw_wallet: *withdraw_wallet,
+            mint: *mint,
+            treasury_token_account: treasury_token_account_address(&presale_state).0,
+            token_program: *token_program,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: instruction::InitializePresale {
+            presale_id: params.presale_id,
+            soft_cap: params.soft_cap,
+            hard_cap: params.hard_cap,
+            price_lamports: params.price_lamports,
+            price_tokens: params.price_tokens,
+            start_time: params.start_time,
+            end_time: params.end_time,
+            min_contribution: params.min_contribution,
+            max_contribution: params.max_contribution,
+            merkle_root: params.merkle_root,
+            vesting: params.vesting,
+            lockup_duration: params.lockup_duration,
+        }
+        .data(),
+    }
+}
+
+/// `allocation` and `proof` are only checked when the presale has an
+/// allowlist; pass `0` and an empty proof otherwise.
+pub fn contribute(
+    presale_state: &Pubkey,
+    contributor: &Pubkey,
+    amount: u64,
+    allocation: u64,
+    proof: Vec<[u8; 32]>,
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::Contribute {
+            contributor: *contributor,
+            presale_state: *presale_state,
+            treasury: treasury_address(presale_state).0,
+            user_contribution: user_contribution_address(presale_state, contributor).0,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: instruction::Contribute { amount, allocation, proof }.data(),
+    }
+}
+
+/// Read-only; simulate it and decode the return data with `decode_contribution_info`.
+pub fn get_contribution(presale_state: &Pubkey, contributor: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::GetContribution {
+            contributor: *contributor,
+            user_contribution: user_contribution_address(presale_state, contributor).0,
+            presale_state: *presale_state,
+        }
+        .to_account_metas(None),
+        data: instruction::GetContribution {}.data(),
+    }
+}
+
+pub fn deposit_sale_tokens(
+    presale_state: &Pubkey,
+    authority: &Pubkey,
+    authority_token_account: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    amount: u64,
+    extra_accounts: &[AccountMeta],
+) -> Instruction {
+    let mut account_metas = accounts::DepositSaleTokens {
+        authority: *authority,
+        presale_state: *presale_state,
+        mint: *mint,
+        treasury_token_account: treasury_token_account_address(presale_state).0,
+        authority_token_account: *authority_token_account,
+        token_program: *token_program,
+    }
+    .to_account_metas(None);
+    account_metas.extend_from_slice(extra_accounts);
+
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: account_metas,
+        data: instruction::DepositSaleTokens { amount }.data(),
+    }
+}
+
+/// Failed or Cancelled presales can withdraw the whole escrow, others only
+/// the surplus above what contributors are still owed.
+pub fn withdraw_sale_tokens(
+    presale_state: &Pubkey,
+    authority: &Pubkey,
+    authority_token_account: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    amount: u64,
+    extra_accounts: &[AccountMeta],
+) -> Instruction {
+    let (treasury_token_account, _) = treasury_token_account_address(presale_state);
+    let mut account_metas = accounts::WithdrawSaleTokens {
+        authority: *authority,
+        presale_state: *presale_state,
+        mint: *mint,
+        treasury_token_account,
+        authority_token_account: *authority_token_account,
+        token_program: *token_program,
+    }
+    .to_account_metas(None);
+    account_metas.extend_from_slice(extra_accounts);
+    
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: account_metas,
+        data: instruction::WithdrawSaleTokens { amount }.data(),
+    }
+}
+
+pub fn set_merkle_root(presale_state: &Pubkey, authority: &Pubkey, merkle_root: Option<[u8; 32]>) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: admin_action_accounts(presale_state, authority),
+        data: instruction::SetMerkleRoot { merkle_root }.data(),
+    }
+}
+
+pub fn enable_claims(presale_state: &Pubkey, authority: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: admin_action_accounts(presale_state, authority),
+        data: instruction::EnableClaims {}.data(),
+    }
+}
+
+/// `user_token_account` must be the contributor's associated token account for `mint`.
+/// Set `with_lockup` for presales with a `lockup_duration`; the contributor then
+/// pays rent for the account's lockup record if it does not exist yet.
+pub fn claim_tokens(
+    presale_state: &Pubkey,
+    contributor: &Pubkey,
+    user_token_account: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    with_lockup: bool,
+    extra_accounts: &[AccountMeta],
+) -> Instruction {
+    let mut account_metas = accounts::ClaimTokens {
+        contributor: *contributor,
+        presale_state: *presale_state,
+        user_contribution: user_contribution_address(presale_state, contributor).0,
+        mint: *mint,
+        treasury_token_account: treasury_token_account_address(presale_state).0,
+        user_token_account: *user_token_account,
+        lockup: with_lockup.then(|| lockup_address(mint, user_token_account).0),
+        token_program: *token_program,
+        system_program: system_program::ID,
+    }
+    .to_account_metas(None);
+    account_metas.extend_from_slice(extra_accounts);
+
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: account_metas,
+        data: instruction::ClaimTokens {}.data(),
+    }
+}
+
+/// Returns the rent of a fully unlocked lockup record to `owner`.
+pub fn close_lockup(owner: &Pubkey, mint: &Pubkey, token_account: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::CloseLockup {
+            owner: *owner,
+            lockup: lockup_address(mint, token_account).0,
+        }
+        .to_account_metas(None),
+        data: instruction::CloseLockup {}.data(),
+    }
+}
+
+pub fn enable_refunds(presale_state: &Pubkey, authority: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: admin_action_accounts(presale_state, authority),
+        data: instruction::EnableRefunds {}.data(),
+    }
+}
+
+pub fn refund(presale_state: &Pubkey, contributor: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::Refund {
+            contributor: *contributor,
+            presale_state: *presale_state,
+            user_contribution: user_contribution_address(presale_state, contributor).0,
+            treasury: treasury_address(presale_state).0,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: instruction::Refund {}.data(),
+    }
+}
+
+pub fn finalize_presale(presale_state: &Pubkey, authority: &Pubkey, withdraw_wallet: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::FinalizePresale {
+            authority: *authority,
+            presale_state: *presale_state,
+            treasury: treasury_address(presale_state).0,
+            withdraw_wallet: *withdraw_wallet,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: instruction::FinalizePresale {}.data(),
+    }
+}
+
+fn admin_action_accounts(presale_state: &Pubkey, authority: &Pubkey) -> Vec<AccountMeta> {
+    accounts::AdminAction {
+        authority: *authority,
+        presale_state: *presale_state,
+    }
+    .to_account_metas(None)
+}
+
+pub fn decode_presale_state(data: &[u8]) -> Result<PresaleState> {
+    PresaleState::try_deserialize(&mut &data[..])
+}
+
+pub fn decode_user_contribution(data: &[u8]) -> Result<UserContribution> {
+    UserContribution::try_deserialize(&mut &data[..])
+}
+
+pub fn decode_lockup_record(data: &[u8]) -> Result<LockupRecord> {
+    LockupRecord::try_deserialize(&mut &data[..])
+}
+
+/// Decodes the return data of a simulated `get_contribution`.
+pub fn decode_contribution_info(return_data: &[u8]) -> Result<ContributionInfo> {
+    ContributionInfo::try_from_slice(return_data).map_err(Into::into)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presale_state_address_matches_program_seeds() {
+        let authority = Pubkey::new_unique();
+        let presale_id = 7u64;
+        let expected = Pubkey::find_program_address(
+            &[b"presale_state", authority.as_ref(), presale_id.to_le_bytes().as_ref()],
+            &presale::ID,
+        );
+        
+        assert_eq!(presale_state_address(&authority, presale_id), expected);
+        assert_ne!(presale_state_address(&authority, presale_id + 1).0, expected.0);
+        assert_ne!(presale_state_address(&Pubkey::new_unique(), presale_id).0, expected.0);
+    }
+
+    #[test]
+    fn lockup_address_matches_program_seeds() {
+        let mint = Pubkey::new_unique();
+        let token_account = Pubkey::new_unique();
+        let expected =
+            Pubkey::find_program_address(&[b"lockup", mint.as_ref(), token_account.as_ref()], &presale::ID);
+        
+        assert_eq!(lockup_address(&mint, &token_account), expected);
+        assert_ne!(lockup_address(&token_account, &mint).0, expected.0);
+    }
+
+    #[test]
+    fn extra_account_meta_list_address_matches_hook_seeds() {
+        let mint = Pubkey::new_unique();
+        let expected =
+            Pubkey::find_program_address(&[b"extra-account-metas", mint.as_ref()], &transfer_hook::ID);
+        
+        assert_eq!(extra_account_meta_list_address(&transfer_hook::ID, &mint), expected);
+    }
+}
@@ -0,0 +1,567 @@
+//! Off-chain client for the token program: PDA derivation, instruction
+//! builders for every entrypoint and account decoders.
+//!
+//! Mints created by the token program always live under Token-2022, so the
+//! builders pass `TOKEN_2022_PROGRAM_ID` as the token program.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::{system_program, sysvar};
+use anchor_lang::{AccountDeserialize, InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::{self, get_associated_token_address_with_program_id};
+use token::{accounts, instruction};
+
+pub use anchor_spl::token_2022::ID as TOKEN_2022_PROGRAM_ID;
+pub use token::{MetadataEntry, MetadataField, Minter, TokenState, TransferFeeArgs, ID as PROGRAM_ID};
+
+/// PDA that holds the mint authority when `pda_mint_authority` is enabled.
+pub fn mint_authority_address(mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"mint_authority", mint.as_ref()], &PROGRAM_ID)
+}
+
+/// PDA that holds the mint's metadata, metadata-pointer, transfer-fee and
+/// transfer-hook authorities on behalf of the token authority.
+pub fn extension_authority_address(mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"extension_authority", mint.as_ref()], &PROGRAM_ID)
+}
+
+/// A registered minter's allowance record.
+pub fn minter_address(token_state: &Pubkey, minter: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"minter", token_state.as_ref(), minter.as_ref()], &PROGRAM_ID)
+}
+
+/// Token-2022 associated token account of `owner` for `mint`.
+pub fn associated_token_address(owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+    get_associated_token_address_with_program_id(owner, mint, &TOKEN_2022_PROGRAM_ID)
+}
+
+/// Token settings for `initialize_token`.
+#[derive(Clone)]
+pub struct InitializeTokenParams {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub decimals: u8,
+    pub supply: u64,
+    pub additional_metadata: Vec<MetadataEntry>,
+    pub transfer_fee: Option<TransferFeeArgs>,
+    pub transfer_hook_program_id: Option<Pubkey>,
+    pub default_account_frozen: bool,
+    /// `None` creates the mint without a freeze authority.
+    pub freeze_authority: Option<Pubkey>,
+    pub pda_mint_authority: bool,
+    /// Only allowed together with `pda_mint_authority`.
+    pub max_supply: Option<u64>,
+}
+
+/// `token_state` and `mint` are fresh keypairs that must sign alongside the
+/// authority. The initial supply goes to the authority's associated token account.
+pub fn initialize_token(
+    authority: &Pubkey,
+    token_state: &Pubkey,
+    mint: &Pubkey,
+    params: InitializeTokenParams,
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::InitializeToken {
+            authority: *authority,
+            token_state: *token_state,
+            mint: *mint,
+            token_account: associated_token_address(authority, mint),
+            extension_authority: extension_authority_address(mint).0,
+            token_program: TOKEN_2022_PROGRAM_ID,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        }
+        .to_account_metas(None),
+        data: instruction::InitializeToken {
+            name: params.name,
+            symbol: params.symbol,
+            uri: params.uri,
+            decimals: params.decimals,
+            supply: params.supply,
+            additional_metadata: params.additional_metadata,
+            transfer_fee: params.transfer_fee,
+            transfer_hook_program_id: params.transfer_hook_program_id,
+            default_account_frozen: params.default_account_frozen,
+            freeze_authority: params.freeze_authority,
+            pda_mint_authority: params.pda_mint_authority,
+            max_supply: params.max_supply,
+        }
+        .data(),
+    }
+}
+
+pub fn update_metadata(
+    authority: &Pubkey,
+    token_state: &Pubkey,
+    mint: &Pubkey,
+    field: MetadataField,
+    value: String,
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::UpdateMetadata {
+            authority: *authority,
+            token_state: *token_state,
+            mint: *mint,
+            extension_authority: extension_authority_address(mint).0,
+            token_program: TOKEN_2022_PROGRAM_ID,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: instruction::UpdateMetadata { field, value }.data(),
+    }
+}
+
+/// Who signs a `mint_tokens` call.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MintSigner {
+    /// The signer is the mint authority itself.
+    MintAuthority,
+    /// The mint is PDA-controlled and the signer is the token authority.
+    TokenAuthority,
+    /// The mint is PDA-controlled and the signer is a registered minter.
+    Minter,
+}
+
+pub fn mint_tokens(
+    authority: &Pubkey,
+    token_state: &Pubkey,
+    mint: &Pubkey,
+    token_account: &Pubkey,
+    amount: u64,
+    signer: MintSigner,
+) -> Instruction {
+    let mint_authority = match signer {
+        MintSigner::MintAuthority => None,
+        MintSigner::TokenAuthority | MintSigner::Minter => Some(mint_authority_address(mint).0),
+    };
+    let minter = match signer {
+        MintSigner::Minter => Some(minter_address(token_state, authority).0),
+        MintSigner::MintAuthority | MintSigner::TokenAuthority => None,
+    };
+
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::MintTokens {
+            authority: *authority,
+            token_state: *token_state,
+            mint: *mint,
+            token_account: *token_account,
+            token_program: TOKEN_2022_PROGRAM_ID,
+            mint_authority,
+            minter,
+        }
+        .to_account_metas(None),
+        data: instruction::MintTokens { amount }.data(),
+    }
+}
+
+pub fn burn_tokens(
+    owner: &Pubkey,
+    token_state: &Pubkey,
+    mint: &Pubkey,
+    token_account: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::BurnTokens {
+            owner: *owner,
+            token_state: *token_state,
+            mint: *mint,
+            token_account: *token_account,
+            token_program: TOKEN_2022_PROGRAM_ID,
+        }
+        .to_account_metas(None),
+        data: instruction::BurnTokens { amount }.data(),
+    }
+}
+
+/// `extra_accounts` carries the transfer-hook accounts for hooked mints.
+pub fn transfer_tokens(
+    owner: &Pubkey,
+    token_state: &Pubkey,
+    mint: &Pubkey,
+    from: &Pubkey,
+    to: &Pubkey,
+    amount: u64,
+    extra_accounts: &[AccountMeta],
+) -> Instruction {
+    let mut account_metas = accounts::TransferTokens {
+        owner: *owner,
+        from: *from,
+        to: *to,
+        mint: *mint,
+        token_state: *token_state,
+        token_program: TOKEN_2022_PROGRAM_ID,
+    }
+    .to_account_metas(None);
+    account_metas.extend_from_slice(extra_accounts);
+
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: account_metas,
+        data: instruction::TransferTokens { amount }.data(),
+    }
+}
+
+pub fn harvest_withheld_fees(token_state: &Pubkey, mint: &Pubkey, sources: &[Pubkey]) -> Instruction {
+    let mut account_metas = accounts::HarvestWithheldFees {
+        token_state: *token_state,
+        mint: *mint,
+        token_program: TOKEN_2022_PROGRAM_ID,
+    }
+    .to_account_metas(None);
+    account_metas.extend(sources.iter().map(|source| AccountMeta::new(*source, false)));
+
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: account_metas,
+        data: instruction::HarvestWithheldFees {}.data(),
+    }
+}
+
+pub fn withdraw_withheld_fees(
+    authority: &Pubkey,
+    token_state: &Pubkey,
+    mint: &Pubkey,
+    destination: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::WithdrawWithheldFees {
+            authority: *authority,
+            token_state: *token_state,
+            mint: *mint,
+            destination: *destination,
+            extension_authority: extension_authority_address(mint).0,
+            token_program: TOKEN_2022_PROGRAM_ID,
+        }
+        .to_account_metas(None),
+        data: instruction::WithdrawWithheldFees {}.data(),
+    }
+}
+
+pub fn set_transfer_fee(
+    authority: &Pubkey,
+    token_state: &Pubkey,
+    mint: &Pubkey,
+    basis_points: u16,
+    maximum_fee: u64,
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::SetTransferFee {
+            authority: *authority,
+            token_state: *token_state,
+            mint: *mint,
+            extension_authority: extension_authority_address(mint).0,
+            token_program: TOKEN_2022_PROGRAM_ID,
+        }
+        .to_account_metas(None),
+        data: instruction::SetTransferFee {
+            basis_points,
+            maximum_fee,
+        }
+        .data(),
+    }
+}
+
+pub fn freeze_account(
+    freeze_authority: &Pubkey,
+    token_state: &Pubkey,
+    mint: &Pubkey,
+    token_account: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::FreezeTokenAccount {
+            freeze_authority: *freeze_authority,
+            token_state: *token_state,
+            mint: *mint,
+            token_account: *token_account,
+            token_program: TOKEN_2022_PROGRAM_ID,
+        }
+        .to_account_metas(None),
+        data: instruction::FreezeAccount {}.data(),
+    }
+}
+
+pub fn thaw_account(
+    freeze_authority: &Pubkey,
+    token_state: &Pubkey,
+    mint: &Pubkey,
+    token_account: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::ThawTokenAccount {
+            freeze_authority: *freeze_authority,
+            token_state: *token_state,
+            mint: *mint,
+            token_account: *token_account,
+            token_program: TOKEN_2022_PROGRAM_ID,
+        }
+        .to_account_metas(None),
+        data: instruction::ThawAccount {}.data(),
+    }
+}
+
+pub fn decrease_max_supply(authority: &Pubkey, token_state: &Pubkey, mint: &Pubkey, new_max_supply: u64) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::DecreaseMaxSupply {
+            authority: *authority,
+            token_state: *token_state,
+            mint: *mint,
+        }
+        .to_account_metas(None),
+        data: instruction::DecreaseMaxSupply { new_max_supply }.data(),
+    }
+}
+
+pub fn set_minter(authority: &Pubkey, token_state: &Pubkey, minter: &Pubkey, allowance: u64) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::SetMinter {
+            authority: *authority,
+            token_state: *token_state,
+            minter_account: minter_address(token_state, minter).0,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: instruction::SetMinter {
+            minter: *minter,
+            allowance,
+        }
+        .data(),
+    }
+}
+
+pub fn remove_minter(authority: &Pubkey, token_state: &Pubkey, minter: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::RemoveMinter {
+            authority: *authority,
+            token_state: *token_state,
+            minter_account: minter_address(token_state, minter).0,
+        }
+        .to_account_metas(None),
+        data: instruction::RemoveMinter {}.data(),
+    }
+}
+
+pub fn propose_authority(authority: &Pubkey, token_state: &Pubkey, new_authority: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::ProposeAuthority {
+            authority: *authority,
+            token_state: *token_state,
+        }
+        .to_account_metas(None),
+        data: instruction::ProposeAuthority {
+            new_authority: *new_authority,
+        }
+        .data(),
+    }
+}
+
+pub fn accept_authority(new_authority: &Pubkey, token_state: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::AcceptAuthority {
+            new_authority: *new_authority,
+            token_state: *token_state,
+        }
+        .to_account_metas(None),
+        data: instruction::AcceptAuthority {}.data(),
+    }
+}
+
+/// `authority` is the wallet mint authority, or the token authority when the
+/// mint is PDA-controlled.
+pub fn set_mint_authority(
+    authority: &Pubkey,
+    token_state: &Pubkey,
+    mint: &Pubkey,
+    new_mint_authority: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: set_mint_authority_accounts(authority, token_state, mint),
+        data: instruction::SetMintAuthority {
+            new_mint_authority: *new_mint_authority,
+        }
+        .data(),
+    }
+}
+
+pub fn renounce_mint_authority(authority: &Pubkey, token_state: &Pubkey, mint: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: set_mint_authority_accounts(authority, token_state, mint),
+        data: instruction::RenounceMintAuthority {}.data(),
+    }
+}
+
+pub fn set_freeze_authority(
+    freeze_authority: &Pubkey,
+    token_state: &Pubkey,
+    mint: &Pubkey,
+    new_freeze_authority: Option<Pubkey>,
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::SetFreezeAuthority {
+            freeze_authority: *freeze_authority,
+            token_state: *token_state,
+            mint: *mint,
+            token_program: TOKEN_2022_PROGRAM_ID,
+        }
+        .to_account_metas(None),
+        data: instruction::SetFreezeAuthority { new_freeze_authority }.data(),
+    }
+}
+
+pub fn approve_tokens(
+    owner: &Pubkey,
+    token_state: &Pubkey,
+    mint: &Pubkey,
+    token_account: &Pubkey,
+    delegate: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::ApproveTokens {
+            owner: *owner,
+            token_state: *token_state,
+            mint: *mint,
+            token_account: *token_account,
+            delegate: *delegate,
+            token_program: TOKEN_2022_PROGRAM_ID,
+        }
+        .to_account_metas(None),
+        data: instruction::ApproveTokens { amount }.data(),
+    }
+}
+
+pub fn revoke_delegate(owner: &Pubkey, token_state: &Pubkey, mint: &Pubkey, token_account: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts::RevokeDelegate {
+            owner: *owner,
+            token_state: *token_state,
+            mint: *mint,
+            token_account: *token_account,
+            token_program: TOKEN_2022_PROGRAM_ID,
+        }
+        .to_account_metas(None),
+        data: instruction::RevokeDelegate {}.data(),
+    }
+}
+
+pub fn increase_allowance(
+    owner: &Pubkey,
+    token_state: &Pubkey,
+    mint: &Pubkey,
+    token_account: &Pubkey,
+    delegate: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: adjust_allowance_accounts(owner, token_state, mint, token_account, delegate),
+        data: instruction::IncreaseAllowance { amount }.data(),
+    }
+}
+
+pub fn decrease_allowance(
+    owner: &Pubkey,
+    token_state: &Pubkey,
+    mint: &Pubkey,
+    token_account: &Pubkey,
+    delegate: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: adjust_allowance_accounts(owner, token_state, mint, token_account, delegate),
+        data: instruction::DecreaseAllowance { amount }.data(),
+    }
+}
+
+fn set_mint_authority_accounts(authority: &Pubkey, token_state: &Pubkey, mint: &Pubkey) -> Vec<AccountMeta> {
+    accounts::SetMintAuthority {
+        authority: *authority,
+        token_state: *token_state,
+        mint: *mint,
+        mint_authority: mint_authority_address(mint).0,
+        token_program: TOKEN_2022_PROGRAM_ID,
+    }
+    .to_account_metas(None)
+}
+
+fn adjust_allowance_accounts(
+    owner: &Pubkey,
+    token_state: &Pubkey,
+    mint: &Pubkey,
+    token_account: &Pubkey,
+    delegate: &Pubkey,
+) -> Vec<AccountMeta> {
+    accounts::AdjustAllowance {
+        owner: *owner,
+        token_state: *token_state,
+        token_account: *token_account,
+        mint: *mint,
+        delegate: *delegate,
+        token_program: TOKEN_2022_PROGRAM_ID,
+    }
+    .to_account_metas(None)
+}
+
+pub fn decode_token_state(data: &[u8]) -> Result<TokenState> {
+    TokenState::try_deserialize(&mut &data[..])
+}
+
+pub fn decode_minter(data: &[u8]) -> Result<Minter> {
+    Minter::try_deserialize(&mut &data[..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mint_authority_address_matches_program_seeds() {
+        let mint = Pubkey::new_unique();
+        let expected = Pubkey::find_program_address(&[b"mint_authority", mint.as_ref()], &token::ID);
+        
+        assert_eq!(mint_authority_address(&mint), expected);
+        assert_ne!(mint_authority_address(&Pubkey::new_unique()).0, expected.0);
+    }
+
+    #[test]
+    fn extension_authority_address_matches_program_seeds() {
+        let mint = Pubkey::new_unique();
+        let expected = Pubkey::find_program_address(&[b"extension_authority", mint.as_ref()], &token::ID);
+        
+        assert_eq!(extension_authority_address(&mint), expected);
+        assert_ne!(extension_authority_address(&mint).0, mint_authority_address(&mint).0);
+    }
+
+    #[test]
+    fn minter_address_matches_program_seeds() {
+        let token_state = Pubkey::new_unique();
+        let minter = Pubkey::new_unique();
+        let expected =
+            Pubkey::find_program_address(&[b"minter", token_state.as_ref(), minter.as_ref()], &token::ID);
+        
+        assert_eq!(minter_address(&token_state, &minter), expected);
+        assert_ne!(minter_address(&minter, &token_state).0, expected.0);
+    }
+}
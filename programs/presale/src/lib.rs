@@ -1,83 +1,192 @@
+// Instruction handlers mirror their on-chain argument lists
+#![allow(clippy::too_many_arguments)]
+
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::keccak;
 use anchor_lang::system_program;
-use anchor_spl::token::{self, Token};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use anchor_spl::token_2022::spl_token_2022;
 
-declare_id!("your_program_pubkey_in_base58");
+declare_id!("FrxkZc1Lnu224H73VmCZC9GKEhhrX3FDYPopdb3HYqC");
 
 #[program]
 pub mod presale {
     use super::*;
 
+    /// Creates the presale along with its SOL treasury and sale-token escrow.
+    ///
+    /// The escrow account exists before any deposit so that, on a mint whose
+    /// new accounts start frozen, the freeze authority can thaw it first.
     pub fn initialize_presale(
         ctx: Context<InitializePresale>,
+        presale_id: u64,
         soft_cap: u64,
         hard_cap: u64,
-        token_price: u64,
+        price_lamports: u64,
+        price_tokens: u64,
         start_time: i64,
         end_time: i64,
         min_contribution: u64,
+        max_contribution: u64,
+        merkle_root: Option<[u8; 32]>,
+        vesting: VestingSchedule,
+        lockup_duration: i64,
     ) -> Result<()> {
+        require!(
+            price_lamports > 0 && price_tokens > 0,
+            PresaleError::InvalidPrice
+        );
+        require!(
+            max_contribution >= min_contribution,
+            PresaleError::InvalidContributionLimits
+        );
+        require!(soft_cap <= hard_cap, PresaleError::InvalidCaps);
+        require!(start_time < end_time, PresaleError::InvalidSaleWindow);
+        require!(
+            vesting.tge_unlock_bps <= MAX_BPS
+                && vesting.cliff_duration >= 0
+                && vesting.vesting_duration >= 0,
+            PresaleError::InvalidVestingSchedule
+        );
+        require!(
+            (0..=MAX_LOCKUP_DURATION).contains(&lockup_duration),
+            PresaleError::InvalidLockupDuration
+        );
+        
         let presale_state = &mut ctx.accounts.presale_state;
         
         presale_state.authority = ctx.accounts.authority.key();
+        presale_state.presale_id = presale_id;
         presale_state.soft_cap = soft_cap;
         presale_state.hard_cap = hard_cap;
-        presale_state.token_price = token_price;
+        presale_state.price_lamports = price_lamports;
+        presale_state.price_tokens = price_tokens;
         presale_state.start_time = start_time;
         presale_state.end_time = end_time;
         presale_state.min_contribution = min_contribution;
+        presale_state.max_contribution = max_contribution;
+        presale_state.merkle_root = merkle_root;
+        presale_state.vesting = vesting;
+        presale_state.claims_start = None;
+        presale_state.lockup_duration = lockup_duration;
         presale_state.total_contributions = 0;
-        presale_state.claims_enabled = false;
-        presale_state.refunds_enabled = false;
-        presale_state.finalized = false;
+        presale_state.total_refunded = 0;
+        presale_state.status = PresaleStatus::Pending;
         presale_state.treasury = ctx.accounts.treasury.key();
+        presale_state.withdraw_wallet = ctx.accounts.withdraw_wallet.key();
+        presale_state.mint = ctx.accounts.mint.key();
+        presale_state.token_program = ctx.accounts.token_program.key();
+        presale_state.tokens_deposited = 0;
+        presale_state.tokens_claimed = 0;
+        presale_state.token_decimals = ctx.accounts.mint.decimals;
         
-        msg!("Presale initialized with soft cap: {}, hard cap: {}", soft_cap, hard_cap);
+        // Make sure a sale that fills the hard cap can still be priced in u64 base units
+        presale_state.tokens_for(hard_cap)?;
+        
+        msg!("Presale {} initialized with soft cap: {}, hard cap: {}", presale_id, soft_cap, hard_cap);
         msg!("Presale period: {} to {}", start_time, end_time);
+        msg!("Sale mint: {}", presale_state.mint);
+        msg!("Raised funds will be withdrawn to {}", presale_state.withdraw_wallet);
+        msg!("Price: {} lamports per {} tokens", price_lamports, price_tokens);
+        msg!("Contribution limits per wallet: {} to {}", min_contribution, max_contribution);
+        msg!("Allowlist enabled: {}", merkle_root.is_some());
+        msg!("Vesting: {} bps at TGE, {}s cliff, {}s linear",
+            vesting.tge_unlock_bps,
+            vesting.cliff_duration,
+            vesting.vesting_duration);
+        msg!("Claimed tokens locked for {}s after TGE", lockup_duration);
+        
+        emit!(PresaleInitialized {
+            presale: presale_state.key(),
+            presale_id,
+            authority: presale_state.authority,
+            mint: presale_state.mint,
+            soft_cap,
+            hard_cap,
+            price_lamports,
+            price_tokens,
+            start_time,
+            end_time,
+        });
+        
+        // Seed the treasury with its rent-exempt minimum so refunds can always
+        // pay contributors back in full without the PDA dropping below rent
+        let rent_exempt_minimum = Rent::get()?.minimum_balance(0);
+        let treasury_lamports = ctx.accounts.treasury.lamports();
+        if treasury_lamports < rent_exempt_minimum {
+            let cpi_accounts = system_program::Transfer {
+                from: ctx.accounts.authority.to_account_info(),
+                to: ctx.accounts.treasury.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+            
+            system_program::transfer(cpi_ctx, rent_exempt_minimum - treasury_lamports)?;
+        }
         
         Ok(())
     }
 
     pub fn contribute(
         ctx: Context<Contribute>,
-        amount: u64
+        amount: u64,
+        allocation: u64,
+        proof: Vec<[u8; 32]>,
     ) -> Result<()> {
         let presale_state = &mut ctx.accounts.presale_state;
         let clock = Clock::get()?;
         
-        require!(
-            clock.unix_timestamp >= presale_state.start_time,
-            PresaleError::PresaleNotStarted
-        );
-        require!(
-            clock.unix_timestamp <= presale_state.end_time,
-            PresaleError::PresaleEnded
-        );
-        require!(!presale_state.finalized, PresaleError::PresaleFinalized);
-        require!(
-            !presale_state.refunds_enabled,
-            PresaleError::RefundsEnabled
-        );
+        presale_state.refresh_status(clock.unix_timestamp)?;
+        match presale_state.status {
+            PresaleStatus::Active => {}
+            PresaleStatus::Pending => return err!(PresaleError::PresaleNotStarted),
+            PresaleStatus::Succeeded | PresaleStatus::Failed => return err!(PresaleError::PresaleEnded),
+            PresaleStatus::Finalized => return err!(PresaleError::PresaleFinalized),
+            PresaleStatus::Cancelled => return err!(PresaleError::RefundsEnabled),
+        }
 
         require!(
             amount >= presale_state.min_contribution,
             PresaleError::ContributionTooSmall
         );
+        require!(
+            presale_state.tokens_for(amount)? > 0,
+            PresaleError::ContributionBelowTokenUnit
+        );
         require!(
             presale_state.total_contributions + amount <= presale_state.hard_cap,
             PresaleError::HardCapExceeded
         );
 
         let user_contribution = &mut ctx.accounts.user_contribution;
-        user_contribution.contributor = ctx.accounts.contributor.key();
-        user_contribution.amount = user_contribution.amount.checked_add(amount)
+        let new_user_amount = user_contribution.amount.checked_add(amount)
             .ok_or(PresaleError::AmountOverflow)?;
-        user_contribution.claimed = false;
+        require!(
+            new_user_amount <= presale_state.max_contribution,
+            PresaleError::ContributionTooLarge
+        );
+        
+        if let Some(root) = presale_state.merkle_root {
+            let leaf = allowlist_leaf(&ctx.accounts.contributor.key(), allocation);
+            require!(
+                verify_merkle_proof(&proof, &root, leaf),
+                PresaleError::InvalidMerkleProof
+            );
+            require!(
+                new_user_amount <= allocation,
+                PresaleError::AllocationExceeded
+            );
+        }
+        
+        user_contribution.contributor = ctx.accounts.contributor.key();
+        user_contribution.amount = new_user_amount;
         user_contribution.refunded = false;
 
         presale_state.total_contributions = presale_state.total_contributions
             .checked_add(amount)
             .ok_or(PresaleError::AmountOverflow)?;
+        
+        // Filling the hard cap closes the sale without waiting for end_time
+        presale_state.refresh_status(clock.unix_timestamp)?;
 
         let ix = anchor_lang::solana_program::system_instruction::transfer(
             &ctx.accounts.contributor.key(),
@@ -97,25 +206,179 @@ pub mod presale {
             amount, 
             ctx.accounts.contributor.key());
         msg!("Total contributions: {}", presale_state.total_contributions);
-
+        
+        emit!(ContributionMade {
+            presale: presale_state.key(),
+            contributor: ctx.accounts.contributor.key(),
+            amount,
+            contributor_total: user_contribution.amount,
+            total_contributions: presale_state.total_contributions,
+            status: presale_state.status,
+        });
+        
         Ok(())
     }
 
-    pub fn get_contribution(ctx: Context<GetContribution>) -> Result<()> {
+    /// Returns a contributor's position through Anchor return data, for
+    /// `simulateTransaction` and CPI callers. Read-only: the presale status is
+    /// resolved against the clock without being written back.
+    pub fn get_contribution(ctx: Context<GetContribution>) -> Result<ContributionInfo> {
         let user_contribution = &ctx.accounts.user_contribution;
-        let presale_state = &ctx.accounts.presale_state;
-        
-        msg!("Contributor: {}", user_contribution.contributor);
-        msg!("Contribution amount: {} lamports", user_contribution.amount);
-        msg!("Claimed: {}", user_contribution.claimed);
-        msg!("Refunded: {}", user_contribution.refunded);
-        
-        if user_contribution.amount > 0 && presale_state.token_price > 0 {
-            let token_amount = user_contribution.amount
-                .checked_div(presale_state.token_price)
-                .unwrap_or(0);
-            msg!("Tokens entitled: {}", token_amount);
-        }
+        let now = Clock::get()?.unix_timestamp;
+        
+        let mut presale_state = PresaleState::clone(&ctx.accounts.presale_state);
+        presale_state.refresh_status(now)?;
+        
+        let tokens_entitled = presale_state.tokens_for(user_contribution.amount)?;
+        let vested_amount = match (presale_state.status, presale_state.claims_start) {
+            (PresaleStatus::Succeeded | PresaleStatus::Finalized, Some(claims_start)) => presale_state
+                .vesting
+                .vested_amount(tokens_entitled, now.saturating_sub(claims_start)),
+            _ => 0,
+        };
+        let refund_eligible = matches!(presale_state.status, PresaleStatus::Failed | PresaleStatus::Cancelled)
+            && !user_contribution.refunded
+            && user_contribution.amount > 0;
+        
+        Ok(ContributionInfo {
+            contributor: user_contribution.contributor,
+            contributed_lamports: user_contribution.amount,
+            tokens_entitled,
+            vested_amount,
+            claimable_amount: vested_amount.saturating_sub(user_contribution.claimed_amount),
+            claimed_amount: user_contribution.claimed_amount,
+            refunded: user_contribution.refunded,
+            refund_eligible,
+            status: presale_state.status,
+        })
+    }
+    
+    /// Moves sale tokens from the authority into the presale escrow.
+    ///
+    /// Hooked mints take remaining accounts; see `presale_client::transfer_hook_accounts`.
+    pub fn deposit_sale_tokens<'info>(
+        ctx: Context<'_, '_, '_, 'info, DepositSaleTokens<'info>>,
+        amount: u64
+    ) -> Result<()> {
+        require!(amount > 0, PresaleError::InvalidDepositAmount);
+        
+        let balance_before = ctx.accounts.treasury_token_account.amount;
+        
+        spl_token_2022::onchain::invoke_transfer_checked(
+            ctx.accounts.token_program.key,
+            ctx.accounts.authority_token_account.to_account_info(),
+            ctx.accounts.mint.to_account_info(),
+            ctx.accounts.treasury_token_account.to_account_info(),
+            ctx.accounts.authority.to_account_info(),
+            ctx.remaining_accounts,
+            amount,
+            ctx.accounts.mint.decimals,
+            &[],
+        )?;
+        
+        // Token-2022 mints can withhold a transfer fee, so credit what actually arrived
+        ctx.accounts.treasury_token_account.reload()?;
+        let received = ctx.accounts.treasury_token_account.amount
+            .checked_sub(balance_before)
+            .ok_or(PresaleError::AmountOverflow)?;
+        
+        let presale_state = &mut ctx.accounts.presale_state;
+        presale_state.tokens_deposited = presale_state.tokens_deposited
+            .checked_add(received)
+            .ok_or(PresaleError::AmountOverflow)?;
+        
+        msg!("Deposited {} sale tokens into presale treasury", received);
+        msg!("Tokens deposited: {}, tokens owed: {}", 
+            presale_state.tokens_deposited, 
+            presale_state.tokens_owed()?);
+        
+        emit!(SaleTokensDeposited {
+            presale: presale_state.key(),
+            amount: received,
+            tokens_deposited: presale_state.tokens_deposited,
+        });
+        
+        Ok(())
+    }
+    
+    /// Returns escrowed sale tokens to the authority.
+    ///
+    /// Once the presale has Failed or been Cancelled the whole escrow can be
+    /// withdrawn; otherwise only the surplus above what contributors are still
+    /// owed. Hooked mints take remaining accounts; see
+    /// `presale_client::transfer_hook_accounts`.
+    pub fn withdraw_sale_tokens<'info>(
+        ctx: Context<'_, '_, '_, 'info, WithdrawSaleTokens<'info>>,
+        amount: u64
+    ) -> Result<()> {
+        require!(amount > 0, PresaleError::InvalidWithdrawAmount);
+        
+        let presale_state = &mut ctx.accounts.presale_state;
+        presale_state.refresh_status(Clock::get()?.unix_timestamp)?;
+        
+        let tokens_held = presale_state.tokens_deposited
+            .checked_sub(presale_state.tokens_claimed)
+            .ok_or(PresaleError::AmountOverflow)?;
+        let withdrawable = match presale_state.status {
+            PresaleStatus::Failed | PresaleStatus::Cancelled => tokens_held,
+            _ => tokens_held.saturating_sub(presale_state.tokens_owed()?),
+        };
+        require!(amount <= withdrawable, PresaleError::WithdrawExceedsSurplus);
+        
+        let presale_key = presale_state.key();
+        let treasury_token_account_bump = ctx.bumps.treasury_token_account;
+        let seeds = &[
+            b"treasury_token_account" as &[u8],
+            presale_key.as_ref(),
+            &[treasury_token_account_bump],
+        ];
+        let signer = &[&seeds[..]];
+        
+        spl_token_2022::onchain::invoke_transfer_checked(
+            ctx.accounts.token_program.key,
+            ctx.accounts.treasury_token_account.to_account_info(),
+            ctx.accounts.mint.to_account_info(),
+            ctx.accounts.authority_token_account.to_account_info(),
+            ctx.accounts.treasury_token_account.to_account_info(),
+            ctx.remaining_accounts,
+            amount,
+            ctx.accounts.mint.decimals,
+            signer,
+        )?;
+        
+        presale_state.tokens_deposited = presale_state.tokens_deposited
+            .checked_sub(amount)
+            .ok_or(PresaleError::AmountOverflow)?;
+        
+        msg!("Withdrew {} sale tokens from presale treasury", amount);
+        msg!("Tokens deposited: {}, tokens claimed: {}", 
+            presale_state.tokens_deposited, 
+            presale_state.tokens_claimed);
+        
+        emit!(SaleTokensWithdrawn {
+            presale: presale_key,
+            amount,
+            tokens_deposited: presale_state.tokens_deposited,
+            status: presale_state.status,
+        });
+        
+        Ok(())
+    }
+    
+    pub fn set_merkle_root(
+        ctx: Context<AdminAction>,
+        merkle_root: Option<[u8; 32]>
+    ) -> Result<()> {
+        let presale_state = &mut ctx.accounts.presale_state;
+        
+        presale_state.merkle_root = merkle_root;
+        
+        msg!("Allowlist enabled: {}", merkle_root.is_some());
+        
+        emit!(AllowlistUpdated {
+            presale: presale_state.key(),
+            merkle_root,
+        });
         
         Ok(())
     }
@@ -128,58 +391,143 @@ pub mod presale {
             PresaleError::Unauthorized
         );
         
+        let clock = Clock::get()?;
+        presale_state.refresh_status(clock.unix_timestamp)?;
+        
         require!(
-            presale_state.total_contributions >= presale_state.soft_cap,
+            presale_state.status != PresaleStatus::Failed,
             PresaleError::SoftCapNotReached
         );
+        require!(
+            presale_state.status == PresaleStatus::Succeeded,
+            PresaleError::PresaleNotSucceeded
+        );
+        
+        require!(
+            presale_state.tokens_deposited >= presale_state.tokens_for(presale_state.total_contributions)?,
+            PresaleError::InsufficientSaleTokens
+        );
+        
+        // Re-enabling would restart every contributor's vesting schedule
+        require!(presale_state.claims_start.is_none(), PresaleError::ClaimsAlreadyEnabled);
         
-        presale_state.claims_enabled = true;
-        presale_state.refunds_enabled = false;
+        presale_state.claims_start = Some(clock.unix_timestamp);
         
         msg!("Claims enabled for presale");
         
+        emit!(ClaimsEnabled {
+            presale: presale_state.key(),
+            claims_start: clock.unix_timestamp,
+            total_contributions: presale_state.total_contributions,
+            tokens_deposited: presale_state.tokens_deposited,
+        });
+        
         Ok(())
     }
     
-    pub fn claim_tokens(ctx: Context<ClaimTokens>) -> Result<()> {
-        let presale_state = &ctx.accounts.presale_state;
+    /// Releases the vested, unclaimed part of a contributor's allocation.
+    ///
+    /// Tokens claimed before `claims_start + lockup_duration` are recorded in
+    /// the lockup record of the contributor's associated token account, which
+    /// the transfer hook enforces. Hooked mints take remaining accounts; see
+    /// `presale_client::transfer_hook_accounts`.
+    pub fn claim_tokens<'info>(ctx: Context<'_, '_, '_, 'info, ClaimTokens<'info>>) -> Result<()> {
+        let presale_state = &mut ctx.accounts.presale_state;
         let user_contribution = &mut ctx.accounts.user_contribution;
         
-        require!(!user_contribution.claimed, PresaleError::AlreadyClaimed);
-        
-        require!(presale_state.claims_enabled, PresaleError::ClaimsNotEnabled);
+        let claims_start = match (presale_state.status, presale_state.claims_start) {
+            (PresaleStatus::Succeeded | PresaleStatus::Finalized, Some(claims_start)) => claims_start,
+            _ => return err!(PresaleError::ClaimsNotEnabled),
+        };
         
         require!(
             ctx.accounts.contributor.key() == user_contribution.contributor,
             PresaleError::Unauthorized
         );
         
-        let token_amount = user_contribution.amount
-            .checked_div(presale_state.token_price)
-            .ok_or(PresaleError::AmountOverflow)?;
-            
-        require!(token_amount > 0, PresaleError::InsufficientTokenBalance);
+        let entitled_amount = presale_state.tokens_for(user_contribution.amount)?;
         
+        require!(entitled_amount > 0, PresaleError::InsufficientTokenBalance);
+        require!(
+            user_contribution.claimed_amount < entitled_amount,
+            PresaleError::AlreadyClaimed
+        );
+        
+        let clock = Clock::get()?;
+        let vested_amount = presale_state.vesting.vested_amount(
+            entitled_amount,
+            clock.unix_timestamp.saturating_sub(claims_start),
+        );
+        let token_amount = vested_amount.saturating_sub(user_contribution.claimed_amount);
+        
+        require!(token_amount > 0, PresaleError::NothingVested);
+        
+        let presale_key = presale_state.key();
         let treasury_token_account_bump = ctx.bumps.treasury_token_account;
-        let seeds = &[b"treasury_token_account" as &[u8], &[treasury_token_account_bump]];
+        let seeds = &[
+            b"treasury_token_account" as &[u8],
+            presale_key.as_ref(),
+            &[treasury_token_account_bump],
+        ];
         let signer = &[&seeds[..]];
         
-        let cpi_accounts = anchor_spl::token::Transfer {
-            from: ctx.accounts.treasury_token_account.to_account_info(),
-            to: ctx.accounts.user_token_account.to_account_info(),
-            authority: ctx.accounts.treasury_token_account.to_account_info(),
-        };
-        
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        spl_token_2022::onchain::invoke_transfer_checked(
+            ctx.accounts.token_program.key,
+            ctx.accounts.treasury_token_account.to_account_info(),
+            ctx.accounts.mint.to_account_info(),
+            ctx.accounts.user_token_account.to_account_info(),
+            ctx.accounts.treasury_token_account.to_account_info(),
+            ctx.remaining_accounts,
+            token_amount,
+            ctx.accounts.mint.decimals,
+            signer,
+        )?;
         
-        anchor_spl::token::transfer(cpi_ctx, token_amount)?;
+        user_contribution.claimed_amount = vested_amount;
+        presale_state.tokens_claimed = presale_state.tokens_claimed
+            .checked_add(token_amount)
+            .ok_or(PresaleError::AmountOverflow)?;
         
-        user_contribution.claimed = true;
+        let unlock_time = claims_start
+            .checked_add(presale_state.lockup_duration)
+            .ok_or(PresaleError::AmountOverflow)?;
+        if unlock_time > clock.unix_timestamp {
+            let lockup = ctx.accounts.lockup.as_mut().ok_or(PresaleError::LockupRecordRequired)?;
+            lockup.owner = ctx.accounts.contributor.key();
+            lockup.token_account = ctx.accounts.user_token_account.key();
+            lockup.mint = ctx.accounts.mint.key();
+            lockup.lock(presale_key, token_amount, unlock_time, clock.unix_timestamp)?;
+            msg!("{} tokens locked until {}", token_amount, unlock_time);
+        }
         
         msg!("Claimed {} tokens for contributor {}", 
             token_amount, 
             user_contribution.contributor);
+        msg!("Claimed so far: {} of {}", vested_amount, entitled_amount);
+        
+        emit!(TokensClaimed {
+            presale: presale_key,
+            contributor: user_contribution.contributor,
+            amount: token_amount,
+            claimed_amount: vested_amount,
+            entitled_amount,
+            tokens_claimed: presale_state.tokens_claimed,
+        });
+        
+        Ok(())
+    }
+    
+    /// Closes a lockup record once every tranche has unlocked, returning its
+    /// rent to the wallet that claimed. A later locked claim recreates it.
+    pub fn close_lockup(ctx: Context<CloseLockup>) -> Result<()> {
+        let lockup = &ctx.accounts.lockup;
+        
+        require!(
+            lockup.locked_amount(Clock::get()?.unix_timestamp) == 0,
+            PresaleError::LockupActive
+        );
+        
+        msg!("Closed lockup record of token account {}", lockup.token_account);
         
         Ok(())
     }
@@ -192,21 +540,39 @@ pub mod presale {
             PresaleError::Unauthorized
         );
         
-        presale_state.refunds_enabled = true;
-        presale_state.claims_enabled = false;
+        presale_state.refresh_status(Clock::get()?.unix_timestamp)?;
+        
+        // Once tokens are being released the sale can no longer be unwound
+        require!(presale_state.claims_start.is_none(), PresaleError::ClaimsAlreadyEnabled);
+        
+        presale_state.transition(PresaleStatus::Cancelled)?;
+        
+        msg!("Presale cancelled, refunds enabled");
         
-        msg!("Refunds enabled for presale");
+        emit!(RefundsEnabled {
+            presale: presale_state.key(),
+            status: presale_state.status,
+            total_contributions: presale_state.total_contributions,
+        });
         
         Ok(())
     }
     
+    /// Returns a contributor's SOL once the presale is Failed or Cancelled.
+    ///
+    /// A sale that ends below its soft cap resolves to Failed on its own, so
+    /// this never waits on the authority.
     pub fn refund(ctx: Context<Refund>) -> Result<()> {
-        let presale_state = &ctx.accounts.presale_state;
+        let presale_state = &mut ctx.accounts.presale_state;
         let user_contribution = &mut ctx.accounts.user_contribution;
         
         require!(!user_contribution.refunded, PresaleError::AlreadyRefunded);
         
-        require!(presale_state.refunds_enabled, PresaleError::RefundsNotEnabled);
+        presale_state.refresh_status(Clock::get()?.unix_timestamp)?;
+        require!(
+            matches!(presale_state.status, PresaleStatus::Failed | PresaleStatus::Cancelled),
+            PresaleError::RefundsNotEnabled
+        );
         
         require!(
             ctx.accounts.contributor.key() == user_contribution.contributor,
@@ -216,8 +582,9 @@ pub mod presale {
         let refund_amount = user_contribution.amount;
         require!(refund_amount > 0, PresaleError::NoRefundAvailable);
         
+        let presale_key = presale_state.key();
         let treasury_bump = ctx.bumps.treasury;
-        let seeds = &[b"treasury" as &[u8], &[treasury_bump]];
+        let seeds = &[b"treasury" as &[u8], presale_key.as_ref(), &[treasury_bump]];
         let signer = &[&seeds[..]];
         
         let ix = anchor_lang::solana_program::system_instruction::transfer(
@@ -237,10 +604,21 @@ pub mod presale {
         )?;
         
         user_contribution.refunded = true;
+        presale_state.total_refunded = presale_state.total_refunded
+            .checked_add(refund_amount)
+            .ok_or(PresaleError::AmountOverflow)?;
         
         msg!("Refunded {} lamports to contributor {}", 
             refund_amount, 
             user_contribution.contributor);
+        msg!("Total refunded: {}", presale_state.total_refunded);
+        
+        emit!(Refunded {
+            presale: presale_key,
+            contributor: user_contribution.contributor,
+            amount: refund_amount,
+            total_refunded: presale_state.total_refunded,
+        });
         
         Ok(())
     }
@@ -253,25 +631,33 @@ pub mod presale {
             PresaleError::Unauthorized
         );
         
+        presale_state.refresh_status(Clock::get()?.unix_timestamp)?;
+        
+        // The sale is over once end_time has passed or the hard cap was filled
         require!(
-            presale_state.claims_enabled && !presale_state.refunds_enabled,
-            PresaleError::CannotFinalize
+            !matches!(presale_state.status, PresaleStatus::Pending | PresaleStatus::Active),
+            PresaleError::PresaleNotEnded
         );
         
+        // Funds only leave once claims are open, so contributors are never left without tokens
         require!(
-            presale_state.total_contributions >= presale_state.soft_cap,
-            PresaleError::SoftCapNotReached
+            presale_state.status == PresaleStatus::Succeeded && presale_state.claims_start.is_some(),
+            PresaleError::CannotFinalize
         );
         
-        let treasury_balance = **ctx.accounts.treasury.try_borrow_lamports()?;
+        // Leave the rent-exempt minimum behind so the treasury PDA stays alive
+        let rent_exempt_minimum = Rent::get()?.minimum_balance(0);
+        let treasury_balance = ctx.accounts.treasury.lamports()
+            .saturating_sub(rent_exempt_minimum);
         
+        let presale_key = presale_state.key();
         let treasury_bump = ctx.bumps.treasury;
-        let seeds = &[b"treasury" as &[u8], &[treasury_bump]];
+        let seeds = &[b"treasury" as &[u8], presale_key.as_ref(), &[treasury_bump]];
         let signer = &[&seeds[..]];
         
         let ix = anchor_lang::solana_program::system_instruction::transfer(
             &ctx.accounts.treasury.key(),
-            &ctx.accounts.admin_wallet.key(),
+            &ctx.accounts.withdraw_wallet.key(),
             treasury_balance,
         );
         
@@ -279,17 +665,24 @@ pub mod presale {
             &ix,
             &[
                 ctx.accounts.treasury.to_account_info(),
-                ctx.accounts.admin_wallet.to_account_info(),
+                ctx.accounts.withdraw_wallet.to_account_info(),
                 ctx.accounts.system_program.to_account_info(),
             ],
             signer,
         )?;
         
-        presale_state.finalized = true;
+        presale_state.transition(PresaleStatus::Finalized)?;
         
-        msg!("Presale finalized. {} lamports transferred to admin wallet {}", 
+        msg!("Presale finalized. {} lamports transferred to withdraw wallet {}",
             treasury_balance, 
-            ctx.accounts.admin_wallet.key());
+            ctx.accounts.withdraw_wallet.key());
+        
+        emit!(PresaleFinalized {
+            presale: presale_key,
+            withdraw_wallet: ctx.accounts.withdraw_wallet.key(),
+            amount: treasury_balance,
+            total_contributions: presale_state.total_contributions,
+        });
         
         Ok(())
     }
@@ -297,14 +690,19 @@ pub mod presale {
 
 #[derive(Accounts)]
 #[instruction(
+    presale_id: u64,
     soft_cap: u64,
     hard_cap: u64,
-    token_price: u64,
+    price_lamports: u64,
+    price_tokens: u64,
     start_time: i64,
     end_time: i64,
-    min_contribution: u64
+    min_contribution: u64,
+    max_contribution: u64,
+    merkle_root: Option<[u8; 32]>,
+    vesting: VestingSchedule,
+    lockup_duration: i64
 )]
-
 pub struct InitializePresale<'info> {
     #[account(mut)]
     pub authority: Signer<'info>,
@@ -313,51 +711,321 @@ pub struct InitializePresale<'info> {
         init,
         payer = authority,
         space = 8 + PresaleState::SIZE,
-        seeds = [b"presale_state"],
+        seeds = [b"presale_state", authority.key().as_ref(), presale_id.to_le_bytes().as_ref()],
         bump
     )]
     pub presale_state: Account<'info, PresaleState>,
     
     #[account(
-        seeds = [b"treasury"],
+        mut,
+        seeds = [b"treasury", presale_state.key().as_ref()],
         bump
     )]
     /// CHECK: This is the PDA that will collect SOL from contributions
     pub treasury: AccountInfo<'info>,
     
+    /// CHECK: This is the wallet raised funds are sent to at finalization
+    pub withdraw_wallet: AccountInfo<'info>,
+    
+    #[account(mint::token_program = token_program)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"treasury_token_account", presale_state.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = treasury_token_account,
+        token::token_program = token_program
+    )]
+    pub treasury_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+    
     pub system_program: Program<'info, System>,
 }
 
 #[account]
+#[derive(Default)]
 pub struct PresaleState {
     pub authority: Pubkey,        // Admin address
+    pub presale_id: u64,          // Identifier folded into the presale PDA seeds
     pub soft_cap: u64,            // Minimum amount to raise in lamports
     pub hard_cap: u64,            // Maximum amount to raise in lamports
-    pub token_price: u64,         // Price per token in lamports
+    pub price_lamports: u64,      // Lamports paid for `price_tokens` whole tokens
+    pub price_tokens: u64,        // Whole tokens received for `price_lamports`
     pub start_time: i64,          // Start timestamp
     pub end_time: i64,            // End timestamp
     pub min_contribution: u64,    // Minimum contribution amount in lamports
+    pub max_contribution: u64,    // Maximum cumulative contribution per wallet in lamports
     pub total_contributions: u64, // Total amount raised in lamports
-    pub claims_enabled: bool,     // Whether token claims are enabled
-    pub refunds_enabled: bool,    // Whether refunds are enabled
-    pub finalized: bool,          // Whether the presale has been finalized
+    pub status: PresaleStatus,    // Lifecycle stage of the presale
+    pub total_refunded: u64,      // Total amount refunded in lamports
     pub treasury: Pubkey,         // Treasury PDA
+    pub withdraw_wallet: Pubkey,  // Destination of raised funds at finalization
+    pub mint: Pubkey,             // Mint of the token being sold
+    pub token_program: Pubkey,    // Token program that owns the mint
+    pub tokens_deposited: u64,    // Sale tokens escrowed in the treasury token account
+    pub tokens_claimed: u64,      // Sale tokens already paid out to contributors
+    pub token_decimals: u8,       // Decimals of the sale mint (TokenState.decimals)
+    pub merkle_root: Option<[u8; 32]>, // Allowlist of (wallet, allocation) leaves, if gated
+    pub vesting: VestingSchedule, // Release schedule for claimed tokens
+    pub claims_start: Option<i64>, // Timestamp claims were enabled (TGE), once they are
+    pub lockup_duration: i64,     // Seconds after TGE during which claimed tokens cannot be transferred
 }
 
 impl PresaleState {
-    pub const SIZE: usize = 32 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + 1 + 1 + 32; // Size in bytes
+    pub const SIZE: usize = 32 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + 8 + 32 + 32 + 32 + 32 + 8 + 8 + 1 + 1 + 32 + VestingSchedule::SIZE + 1 + 8 + 8; // Size in bytes
+    
+    /// Applies the time-based transitions: the sale opens at `start_time` and
+    /// resolves once `end_time` has passed or the hard cap has been filled,
+    /// succeeding only if the soft cap was reached.
+    pub fn refresh_status(&mut self, now: i64) -> Result<()> {
+        if self.status == PresaleStatus::Pending && now >= self.start_time {
+            self.transition(PresaleStatus::Active)?;
+        }
+        
+        if self.status == PresaleStatus::Active
+            && (now > self.end_time || self.total_contributions >= self.hard_cap)
+        {
+            if self.total_contributions >= self.soft_cap {
+                self.transition(PresaleStatus::Succeeded)?;
+            } else {
+                self.transition(PresaleStatus::Failed)?;
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Moves the presale to `next`, rejecting transitions the lifecycle does not allow.
+    pub fn transition(&mut self, next: PresaleStatus) -> Result<()> {
+        require!(
+            self.status.can_transition_to(next),
+            PresaleError::InvalidStatusTransition
+        );
+        
+        self.status = next;
+        Ok(())
+    }
+    
+    /// Number of sale tokens, in base units, bought by `lamports`.
+    ///
+    /// The price is `price_lamports` per `price_tokens` whole tokens, scaled by
+    /// the mint decimals with u128 intermediates. The result is rounded down:
+    /// contributors never receive a fraction of a base unit, and the lamports
+    /// left over by rounding stay in the treasury and are swept at finalization.
+    pub fn tokens_for(&self, lamports: u64) -> Result<u64> {
+        let scale = 10u128
+            .checked_pow(self.token_decimals as u32)
+            .ok_or(PresaleError::AmountOverflow)?;
+        let base_units = (lamports as u128)
+            .checked_mul(self.price_tokens as u128)
+            .and_then(|value| value.checked_mul(scale))
+            .and_then(|value| value.checked_div(self.price_lamports as u128))
+            .ok_or(PresaleError::AmountOverflow)?;
+        
+        u64::try_from(base_units).map_err(|_| error!(PresaleError::AmountOverflow))
+    }
+    
+    /// Sale tokens still owed to contributors who have not claimed yet.
+    pub fn tokens_owed(&self) -> Result<u64> {
+        self.tokens_for(self.total_contributions)?
+            .checked_sub(self.tokens_claimed)
+            .ok_or_else(|| error!(PresaleError::AmountOverflow))
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum PresaleStatus {
+    #[default]
+    Pending,                      // Waiting for start_time
+    Active,                       // Accepting contributions
+    Succeeded,                    // Ended with the soft cap reached
+    Failed,                       // Ended below the soft cap, contributors can refund
+    Finalized,                    // Raised funds withdrawn by the authority
+    Cancelled,                    // Cancelled by the authority, contributors can refund
+}
+
+impl PresaleStatus {
+    pub fn can_transition_to(self, next: PresaleStatus) -> bool {
+        use PresaleStatus::*;
+        
+        matches!(
+            (self, next),
+            (Pending, Active)
+                | (Pending, Cancelled)
+                | (Active, Succeeded)
+                | (Active, Failed)
+                | (Active, Cancelled)
+                | (Succeeded, Finalized)
+                | (Succeeded, Cancelled)
+        )
+    }
+}
+
+/// Basis point denominator used for the TGE unlock share.
+pub const MAX_BPS: u16 = 10_000;
+
+/// Longest transfer lockup a presale may set, five years in seconds.
+pub const MAX_LOCKUP_DURATION: i64 = 5 * 365 * 24 * 60 * 60;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct VestingSchedule {
+    pub tge_unlock_bps: u16,      // Share unlocked when claims are enabled, in basis points
+    pub cliff_duration: i64,      // Seconds after TGE before linear vesting starts
+    pub vesting_duration: i64,    // Seconds over which the rest vests linearly after the cliff
+}
+
+impl VestingSchedule {
+    pub const SIZE: usize = 2 + 8 + 8; // Size in bytes
+    
+    /// Portion of `total` released `elapsed` seconds after claims were enabled.
+    ///
+    /// The TGE share is available immediately, nothing more unlocks until the
+    /// cliff has passed, then the remainder vests linearly (rounded down) over
+    /// `vesting_duration`. A zero duration releases everything at the cliff.
+    pub fn vested_amount(&self, total: u64, elapsed: i64) -> u64 {
+        if elapsed < 0 {
+            return 0;
+        }
+        
+        let total = total as u128;
+        let unlocked_at_tge = total * self.tge_unlock_bps as u128 / MAX_BPS as u128;
+        if elapsed < self.cliff_duration {
+            return unlocked_at_tge as u64;
+        }
+        
+        let vesting_elapsed = elapsed - self.cliff_duration;
+        if vesting_elapsed >= self.vesting_duration {
+            return total as u64;
+        }
+        
+        let linear = (total - unlocked_at_tge) * vesting_elapsed as u128
+            / self.vesting_duration as u128;
+        (unlocked_at_tge + linear) as u64
+    }
+}
+
+/// Allowlist leaf committing to a wallet and the most it may contribute in lamports.
+///
+/// Leaves hash 40 bytes while interior nodes hash 64, so a node can never be
+/// passed off as a leaf.
+pub fn allowlist_leaf(contributor: &Pubkey, allocation: u64) -> [u8; 32] {
+    keccak::hashv(&[contributor.as_ref(), &allocation.to_le_bytes()]).to_bytes()
+}
+
+/// Verifies a keccak Merkle proof whose sibling pairs are hashed in sorted order.
+pub fn verify_merkle_proof(proof: &[[u8; 32]], root: &[u8; 32], leaf: [u8; 32]) -> bool {
+    let computed = proof.iter().fold(leaf, |node, sibling| {
+        if node <= *sibling {
+            keccak::hashv(&[&node, sibling]).to_bytes()
+        } else {
+            keccak::hashv(&[sibling, &node]).to_bytes()
+        }
+    });
+    
+    computed == *root
+}
+
+/// Transfer lockup for presale tokens, read by the transfer hook program.
+///
+/// Keyed by mint and the token account claims were paid into rather than by
+/// presale or wallet, because the hook only sees the token accounts involved
+/// in a transfer and only the source account's balance is checked. The lock
+/// follows the account, so it holds even if the account changes owner, and
+/// the wallet's other token accounts of the mint are not restricted.
+///
+/// Each presale that paid tokens into the account keeps its own tranche, so
+/// tokens from different rounds of the same mint unlock on their own
+/// schedules. A tranche only covers tokens its presale paid out, so a presale
+/// created by any authority cannot lock tokens it did not distribute.
+#[account]
+pub struct LockupRecord {
+    pub owner: Pubkey,            // Wallet that claimed the locked tokens
+    pub token_account: Pubkey,    // Token account holding the locked tokens
+    pub mint: Pubkey,             // Mint of the locked tokens
+    pub tranches: Vec<LockupTranche>, // Tranches not yet unlocked, at most MAX_LOCKUP_TRANCHES
+}
+
+impl LockupRecord {
+    pub const SIZE: usize = 32 + 32 + 32 + 4 + MAX_LOCKUP_TRANCHES * LockupTranche::SIZE; // Size in bytes
+    
+    /// Balance the token account must keep at `now`.
+    pub fn locked_amount(&self, now: i64) -> u64 {
+        self.tranches
+            .iter()
+            .filter(|tranche| now < tranche.unlock_time)
+            .fold(0u64, |locked, tranche| locked.saturating_add(tranche.amount))
+    }
+    
+    /// Adds `amount` to the tranche of `presale`, first dropping tranches that
+    /// have unlocked by `now`.
+    pub fn lock(&mut self, presale: Pubkey, amount: u64, unlock_time: i64, now: i64) -> Result<()> {
+        self.tranches.retain(|tranche| now < tranche.unlock_time);
+        
+        match self.tranches.iter_mut().find(|tranche| tranche.presale == presale) {
+            Some(tranche) => {
+                tranche.amount = tranche.amount
+                    .checked_add(amount)
+                    .ok_or(PresaleError::AmountOverflow)?;
+            }
+            None => {
+                require!(
+                    self.tranches.len() < MAX_LOCKUP_TRANCHES,
+                    PresaleError::TooManyLockupTranches
+                );
+                self.tranches.push(LockupTranche {
+                    presale,
+                    amount,
+                    unlock_time,
+                });
+            }
+        }
+        
+        Ok(())
+    }
+}
+
+/// Most presales whose lockups a single token account can carry at once.
+pub const MAX_LOCKUP_TRANCHES: usize = 8;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct LockupTranche {
+    pub presale: Pubkey,          // Presale that paid out the locked tokens
+    pub amount: u64,              // Tokens locked by that presale
+    pub unlock_time: i64,         // That presale's claims_start + lockup_duration
+}
+
+impl LockupTranche {
+    pub const SIZE: usize = 32 + 8 + 8; // Size in bytes
 }
 
 #[account]
 pub struct UserContribution {
     pub contributor: Pubkey,      // User's wallet address
     pub amount: u64,              // Contribution amount in lamports
-    pub claimed: bool,            // Whether user has claimed tokens
+    pub claimed_amount: u64,      // Tokens already claimed from the vested allocation
     pub refunded: bool,           // Whether user has received a refund
 }
 
 impl UserContribution {
-    pub const SIZE: usize = 32 + 8 + 1 + 1; // Size in bytes
+    pub const SIZE: usize = 32 + 8 + 8 + 1; // Size in bytes
+}
+
+/// Snapshot of a contributor's position returned by `get_contribution`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct ContributionInfo {
+    pub contributor: Pubkey,
+    pub contributed_lamports: u64,
+    pub tokens_entitled: u64,     // Tokens bought at the presale price
+    pub vested_amount: u64,       // Tokens released by the vesting schedule so far
+    pub claimable_amount: u64,    // Vested tokens not yet claimed
+    pub claimed_amount: u64,
+    pub refunded: bool,
+    pub refund_eligible: bool,    // Whether `refund` would currently pay out
+    pub status: PresaleStatus,
 }
 
 #[derive(Accounts)]
@@ -368,14 +1036,14 @@ pub struct Contribute<'info> {
     
     #[account(
         mut,
-        seeds = [b"presale_state"],
+        seeds = [b"presale_state", presale_state.authority.as_ref(), presale_state.presale_id.to_le_bytes().as_ref()],
         bump
     )]
     pub presale_state: Account<'info, PresaleState>,
     
     #[account(
         mut,
-        seeds = [b"treasury"],
+        seeds = [b"treasury", presale_state.key().as_ref()],
         bump
     )]
     /// CHECK: This is the PDA that collects SOL from contributions
@@ -385,7 +1053,7 @@ pub struct Contribute<'info> {
         init_if_needed,
         payer = contributor,
         space = 8 + UserContribution::SIZE,
-        seeds = [b"user_contribution", contributor.key().as_ref()],
+        seeds = [b"user_contribution", presale_state.key().as_ref(), contributor.key().as_ref()],
         bump
     )]
     pub user_contribution: Account<'info, UserContribution>,
@@ -399,13 +1067,13 @@ pub struct GetContribution<'info> {
     pub contributor: AccountInfo<'info>,
     
     #[account(
-        seeds = [b"user_contribution", contributor.key.as_ref()],
+        seeds = [b"user_contribution", presale_state.key().as_ref(), contributor.key.as_ref()],
         bump
     )]
     pub user_contribution: Account<'info, UserContribution>,
     
     #[account(
-        seeds = [b"presale_state"],
+        seeds = [b"presale_state", presale_state.authority.as_ref(), presale_state.presale_id.to_le_bytes().as_ref()],
         bump
     )]
     pub presale_state: Account<'info, PresaleState>,
@@ -417,44 +1085,169 @@ pub struct AdminAction<'info> {
     
     #[account(
         mut,
-        seeds = [b"presale_state"],
+        seeds = [b"presale_state", presale_state.authority.as_ref(), presale_state.presale_id.to_le_bytes().as_ref()],
+        bump,
+        constraint = presale_state.authority == authority.key() @ PresaleError::Unauthorized
+    )]
+    pub presale_state: Account<'info, PresaleState>,
+}
+
+#[derive(Accounts)]
+pub struct DepositSaleTokens<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"presale_state", presale_state.authority.as_ref(), presale_state.presale_id.to_le_bytes().as_ref()],
         bump,
         constraint = presale_state.authority == authority.key() @ PresaleError::Unauthorized
     )]
     pub presale_state: Account<'info, PresaleState>,
+    
+    #[account(
+        address = presale_state.mint @ PresaleError::InvalidMint,
+        mint::token_program = token_program
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(
+        mut,
+        seeds = [b"treasury_token_account", presale_state.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = treasury_token_account,
+        token::token_program = token_program
+    )]
+    pub treasury_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = authority,
+        token::token_program = token_program
+    )]
+    pub authority_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = presale_state.token_program @ PresaleError::InvalidTokenProgram)]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawSaleTokens<'info> {
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"presale_state", presale_state.authority.as_ref(), presale_state.presale_id.to_le_bytes().as_ref()],
+        bump,
+        constraint = presale_state.authority == authority.key() @ PresaleError::Unauthorized
+    )]
+    pub presale_state: Account<'info, PresaleState>,
+    
+    #[account(
+        address = presale_state.mint @ PresaleError::InvalidMint,
+        mint::token_program = token_program
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(
+        mut,
+        seeds = [b"treasury_token_account", presale_state.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = treasury_token_account,
+        token::token_program = token_program
+    )]
+    pub treasury_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = authority,
+        token::token_program = token_program
+    )]
+    pub authority_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = presale_state.token_program @ PresaleError::InvalidTokenProgram)]
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 #[derive(Accounts)]
 pub struct ClaimTokens<'info> {
+    #[account(mut)]
     pub contributor: Signer<'info>,
     
     #[account(
-        seeds = [b"presale_state"],
+        mut,
+        seeds = [b"presale_state", presale_state.authority.as_ref(), presale_state.presale_id.to_le_bytes().as_ref()],
         bump
     )]
     pub presale_state: Account<'info, PresaleState>,
     
     #[account(
         mut,
-        seeds = [b"user_contribution", contributor.key().as_ref()],
+        seeds = [b"user_contribution", presale_state.key().as_ref(), contributor.key().as_ref()],
         bump,
         constraint = user_contribution.contributor == contributor.key() @ PresaleError::Unauthorized
     )]
     pub user_contribution: Account<'info, UserContribution>,
     
+    #[account(
+        address = presale_state.mint @ PresaleError::InvalidMint,
+        mint::token_program = token_program
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(
+        mut,
+        seeds = [b"treasury_token_account", presale_state.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = treasury_token_account,
+        token::token_program = token_program
+    )]
+    pub treasury_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    // Token-2022 associated token accounts carry ImmutableOwner, so the
+    // lockup cannot be sidestepped by handing the account to another wallet
     #[account(
         mut,
-        seeds = [b"treasury_token_account"],
+        associated_token::mint = mint,
+        associated_token::authority = contributor,
+        associated_token::token_program = token_program
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    /// Only needed, and only paid for, when the presale has a lockup
+    #[account(
+        init_if_needed,
+        payer = contributor,
+        space = 8 + LockupRecord::SIZE,
+        seeds = [b"lockup", mint.key().as_ref(), user_token_account.key().as_ref()],
         bump
     )]
-    /// CHECK: This is the PDA that holds tokens for distribution
-    pub treasury_token_account: AccountInfo<'info>,
+    pub lockup: Option<Account<'info, LockupRecord>>,
+    
+    #[account(address = presale_state.token_program @ PresaleError::InvalidTokenProgram)]
+    pub token_program: Interface<'info, TokenInterface>,
     
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseLockup<'info> {
     #[account(mut)]
-    /// CHECK: This is the user's token account to receive tokens
-    pub user_token_account: AccountInfo<'info>,
+    pub owner: Signer<'info>,
     
-    pub token_program: Program<'info, Token>,
+    #[account(
+        mut,
+        close = owner,
+        seeds = [b"lockup", lockup.mint.as_ref(), lockup.token_account.as_ref()],
+        bump,
+        constraint = lockup.owner == owner.key() @ PresaleError::Unauthorized
+    )]
+    pub lockup: Account<'info, LockupRecord>,
 }
 
 #[derive(Accounts)]
@@ -463,14 +1256,15 @@ pub struct Refund<'info> {
     pub contributor: Signer<'info>,
     
     #[account(
-        seeds = [b"presale_state"],
+        mut,
+        seeds = [b"presale_state", presale_state.authority.as_ref(), presale_state.presale_id.to_le_bytes().as_ref()],
         bump
     )]
     pub presale_state: Account<'info, PresaleState>,
     
     #[account(
         mut,
-        seeds = [b"user_contribution", contributor.key().as_ref()],
+        seeds = [b"user_contribution", presale_state.key().as_ref(), contributor.key().as_ref()],
         bump,
         constraint = user_contribution.contributor == contributor.key() @ PresaleError::Unauthorized
     )]
@@ -478,7 +1272,7 @@ pub struct Refund<'info> {
     
     #[account(
         mut,
-        seeds = [b"treasury"],
+        seeds = [b"treasury", presale_state.key().as_ref()],
         bump
     )]
     /// CHECK: This is the PDA that holds the SOL contributions
@@ -493,7 +1287,7 @@ pub struct FinalizePresale<'info> {
     
     #[account(
         mut,
-        seeds = [b"presale_state"],
+        seeds = [b"presale_state", presale_state.authority.as_ref(), presale_state.presale_id.to_le_bytes().as_ref()],
         bump,
         constraint = presale_state.authority == authority.key() @ PresaleError::Unauthorized
     )]
@@ -501,19 +1295,108 @@ pub struct FinalizePresale<'info> {
     
     #[account(
         mut,
-        seeds = [b"treasury"],
+        seeds = [b"treasury", presale_state.key().as_ref()],
         bump
     )]
     /// CHECK: This is the PDA that holds the SOL contributions
     pub treasury: AccountInfo<'info>,
     
-    #[account(mut)]
-    /// CHECK: This is the admin's wallet to receive funds
-    pub admin_wallet: AccountInfo<'info>,
+    #[account(
+        mut,
+        address = presale_state.withdraw_wallet @ PresaleError::InvalidWithdrawWallet
+    )]
+    /// CHECK: This is the withdraw wallet recorded at initialization
+    pub withdraw_wallet: AccountInfo<'info>,
     
     pub system_program: Program<'info, System>,
 }
 
+#[event]
+pub struct PresaleInitialized {
+    pub presale: Pubkey,
+    pub presale_id: u64,
+    pub authority: Pubkey,
+    pub mint: Pubkey,
+    pub soft_cap: u64,
+    pub hard_cap: u64,
+    pub price_lamports: u64,
+    pub price_tokens: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+}
+
+#[event]
+pub struct ContributionMade {
+    pub presale: Pubkey,
+    pub contributor: Pubkey,
+    pub amount: u64,
+    pub contributor_total: u64,
+    pub total_contributions: u64,
+    pub status: PresaleStatus,
+}
+
+#[event]
+pub struct SaleTokensDeposited {
+    pub presale: Pubkey,
+    pub amount: u64,
+    pub tokens_deposited: u64,
+}
+
+#[event]
+pub struct SaleTokensWithdrawn {
+    pub presale: Pubkey,
+    pub amount: u64,
+    pub tokens_deposited: u64,
+    pub status: PresaleStatus,
+}
+
+#[event]
+pub struct AllowlistUpdated {
+    pub presale: Pubkey,
+    pub merkle_root: Option<[u8; 32]>,
+}
+
+#[event]
+pub struct ClaimsEnabled {
+    pub presale: Pubkey,
+    pub claims_start: i64,
+    pub total_contributions: u64,
+    pub tokens_deposited: u64,
+}
+
+#[event]
+pub struct TokensClaimed {
+    pub presale: Pubkey,
+    pub contributor: Pubkey,
+    pub amount: u64,
+    pub claimed_amount: u64,
+    pub entitled_amount: u64,
+    pub tokens_claimed: u64,
+}
+
+#[event]
+pub struct RefundsEnabled {
+    pub presale: Pubkey,
+    pub status: PresaleStatus,
+    pub total_contributions: u64,
+}
+
+#[event]
+pub struct Refunded {
+    pub presale: Pubkey,
+    pub contributor: Pubkey,
+    pub amount: u64,
+    pub total_refunded: u64,
+}
+
+#[event]
+pub struct PresaleFinalized {
+    pub presale: Pubkey,
+    pub withdraw_wallet: Pubkey,
+    pub amount: u64,
+    pub total_contributions: u64,
+}
+
 #[error_code]
 pub enum PresaleError {
     #[msg("Presale has not started yet")]
@@ -548,4 +1431,386 @@ pub enum PresaleError {
     NoRefundAvailable,
     #[msg("Cannot finalize presale")]
     CannotFinalize,
+    #[msg("Mint does not match the presale mint")]
+    InvalidMint,
+    #[msg("Token program does not match the presale token program")]
+    InvalidTokenProgram,
+    #[msg("Deposit amount must be greater than zero")]
+    InvalidDepositAmount,
+    #[msg("Deposited sale tokens do not cover the tokens owed to contributors")]
+    InsufficientSaleTokens,
+    #[msg("Price must have a non-zero lamport and token amount")]
+    InvalidPrice,
+    #[msg("Contribution is too small to buy a single token base unit")]
+    ContributionBelowTokenUnit,
+    #[msg("Maximum contribution must not be below the minimum contribution")]
+    InvalidContributionLimits,
+    #[msg("Contribution would exceed the per-wallet maximum")]
+    ContributionTooLarge,
+    #[msg("Wallet is not on the presale allowlist")]
+    InvalidMerkleProof,
+    #[msg("Contribution would exceed the wallet's allowlist allocation")]
+    AllocationExceeded,
+    #[msg("Vesting schedule is invalid")]
+    InvalidVestingSchedule,
+    #[msg("Claims are already enabled")]
+    ClaimsAlreadyEnabled,
+    #[msg("No tokens have vested since the last claim")]
+    NothingVested,
+    #[msg("Presale cannot move to the requested status")]
+    InvalidStatusTransition,
+    #[msg("Presale has not ended successfully")]
+    PresaleNotSucceeded,
+    #[msg("Presale has not ended yet")]
+    PresaleNotEnded,
+    #[msg("Withdraw wallet does not match the presale withdraw wallet")]
+    InvalidWithdrawWallet,
+    #[msg("Lockup duration must be between zero and five years")]
+    InvalidLockupDuration,
+    #[msg("Withdraw amount must be greater than zero")]
+    InvalidWithdrawAmount,
+    #[msg("Withdrawal would dip into sale tokens still owed to contributors")]
+    WithdrawExceedsSurplus,
+    #[msg("Token account already carries the maximum number of presale lockups")]
+    TooManyLockupTranches,
+    #[msg("Locked claims need the token account's lockup record")]
+    LockupRecordRequired,
+    #[msg("Lockup record still holds locked tokens")]
+    LockupActive,
+    #[msg("Soft cap must not exceed the hard cap")]
+    InvalidCaps,
+    #[msg("Presale must end after it starts")]
+    InvalidSaleWindow,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn presale_state(price_lamports: u64, price_tokens: u64, token_decimals: u8) -> PresaleState {
+        PresaleState {
+            price_lamports,
+            price_tokens,
+            token_decimals,
+            ..Default::default()
+        }
+    }
+    
+    #[test]
+    fn tokens_for_divides_exactly() {
+        // 1 SOL buys 1000 whole tokens of a 6-decimal mint
+        let state = presale_state(1_000_000_000, 1_000, 6);
+        
+        assert_eq!(state.tokens_for(2_000_000_000).unwrap(), 2_000_000_000);
+        assert_eq!(state.tokens_for(1_000_000).unwrap(), 1_000_000);
+        assert_eq!(state.tokens_for(0).unwrap(), 0);
+    }
+    
+    #[test]
+    fn tokens_for_rounds_down() {
+        let state = presale_state(3, 1, 0);
+        
+        assert_eq!(state.tokens_for(10).unwrap(), 3);
+        assert_eq!(state.tokens_for(2).unwrap(), 0);
+    }
+    
+    #[test]
+    fn tokens_for_handles_products_above_u64() {
+        // lamports * price_tokens overflows u64 but the quotient fits
+        let state = presale_state(4, 2, 0);
+        assert_eq!(state.tokens_for(u64::MAX).unwrap(), u64::MAX / 2);
+        
+        // The quotient itself no longer fits in u64
+        let state = presale_state(1, 2, 0);
+        assert!(state.tokens_for(u64::MAX).is_err());
+        
+        let state = presale_state(1, 1, 18);
+        assert!(state.tokens_for(u64::MAX).is_err());
+    }
+    
+    fn hash_pair(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+        if a <= b {
+            keccak::hashv(&[a, b]).to_bytes()
+        } else {
+            keccak::hashv(&[b, a]).to_bytes()
+        }
+    }
+    
+    #[test]
+    fn merkle_proof_accepts_single_leaf_root_with_empty_proof() {
+        let wallet = Pubkey::new_unique();
+        let root = allowlist_leaf(&wallet, 100);
+        
+        assert!(verify_merkle_proof(&[], &root, allowlist_leaf(&wallet, 100)));
+        assert!(!verify_merkle_proof(&[], &root, allowlist_leaf(&wallet, 101)));
+    }
+    
+    #[test]
+    fn merkle_proof_accepts_two_leaf_tree() {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (leaf_a, leaf_b) = (allowlist_leaf(&a, 100), allowlist_leaf(&b, 200));
+        let root = hash_pair(&leaf_a, &leaf_b);
+        
+        assert!(verify_merkle_proof(&[leaf_b], &root, leaf_a));
+        assert!(verify_merkle_proof(&[leaf_a], &root, leaf_b));
+    }
+    
+    #[test]
+    fn merkle_proof_accepts_three_leaf_tree() {
+        let (a, b, c) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let leaves = [allowlist_leaf(&a, 100), allowlist_leaf(&b, 200), allowlist_leaf(&c, 300)];
+        let node_ab = hash_pair(&leaves[0], &leaves[1]);
+        let root = hash_pair(&node_ab, &leaves[2]);
+        
+        assert!(verify_merkle_proof(&[leaves[1], leaves[2]], &root, leaves[0]));
+        assert!(verify_merkle_proof(&[leaves[0], leaves[2]], &root, leaves[1]));
+        assert!(verify_merkle_proof(&[node_ab], &root, leaves[2]));
+    }
+    
+    #[test]
+    fn merkle_proof_rejects_wrong_allocation() {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let leaf_b = allowlist_leaf(&b, 200);
+        let root = hash_pair(&allowlist_leaf(&a, 100), &leaf_b);
+        
+        assert!(!verify_merkle_proof(&[leaf_b], &root, allowlist_leaf(&a, 1_000)));
+    }
+    
+    #[test]
+    fn merkle_proof_ignores_sibling_side_but_not_level_order() {
+        let (a, b, c) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let leaves = [allowlist_leaf(&a, 100), allowlist_leaf(&b, 200), allowlist_leaf(&c, 300)];
+        
+        // Pairs are hashed sorted, so a tree built with the siblings swapped has the same root
+        let root = hash_pair(&hash_pair(&leaves[0], &leaves[1]), &leaves[2]);
+        let swapped_root = hash_pair(&leaves[2], &hash_pair(&leaves[1], &leaves[0]));
+        assert_eq!(root, swapped_root);
+        assert!(verify_merkle_proof(&[leaves[1], leaves[2]], &swapped_root, leaves[0]));
+        
+        // Reordering the proof levels changes the path and must fail
+        assert!(!verify_merkle_proof(&[leaves[2], leaves[1]], &root, leaves[0]));
+    }
+    
+    fn schedule(tge_unlock_bps: u16, cliff_duration: i64, vesting_duration: i64) -> VestingSchedule {
+        VestingSchedule {
+            tge_unlock_bps,
+            cliff_duration,
+            vesting_duration,
+        }
+    }
+    
+    #[test]
+    fn vesting_releases_only_tge_share_before_cliff() {
+        let vesting = schedule(1_000, 100, 1_000);
+        
+        assert_eq!(vesting.vested_amount(10_000, -1), 0);
+        assert_eq!(vesting.vested_amount(10_000, 0), 1_000);
+        assert_eq!(vesting.vested_amount(10_000, 99), 1_000);
+        
+        // Without a TGE share nothing is released before the cliff
+        let vesting = schedule(0, 100, 1_000);
+        assert_eq!(vesting.vested_amount(10_000, 0), 0);
+        assert_eq!(vesting.vested_amount(10_000, 99), 0);
+    }
+    
+    #[test]
+    fn vesting_steps_linearly_after_cliff() {
+        let vesting = schedule(1_000, 100, 1_000);
+        
+        assert_eq!(vesting.vested_amount(10_000, 100), 1_000);
+        assert_eq!(vesting.vested_amount(10_000, 101), 1_009);
+        assert_eq!(vesting.vested_amount(10_000, 600), 5_500);
+    }
+    
+    #[test]
+    fn vesting_releases_everything_at_end() {
+        let vesting = schedule(1_000, 100, 1_000);
+        
+        assert_eq!(vesting.vested_amount(10_000, 1_099), 9_991);
+        assert_eq!(vesting.vested_amount(10_000, 1_100), 10_000);
+        assert_eq!(vesting.vested_amount(10_000, i64::MAX), 10_000);
+        
+        // A zero-length vesting period releases everything once the cliff passes
+        let vesting = schedule(0, 100, 0);
+        assert_eq!(vesting.vested_amount(10_000, 100), 10_000);
+    }
+    
+    #[test]
+    fn vesting_is_monotonic_and_rounds_down() {
+        let vesting = schedule(333, 7, 97);
+        let total = 1_000_003u64;
+        let tge = total as u128 * 333 / MAX_BPS as u128;
+        let mut previous = 0;
+        
+        for elapsed in 0..=7 + 97 {
+            let vested = vesting.vested_amount(total, elapsed);
+            let linear_elapsed = (elapsed - 7).clamp(0, 97) as u128;
+            let exact_floor = tge + (total as u128 - tge) * linear_elapsed / 97;
+            
+            assert!(vested >= previous);
+            assert_eq!(vested as u128, exact_floor);
+            previous = vested;
+        }
+        assert_eq!(previous, total);
+    }
+    
+    #[test]
+    fn vesting_does_not_overflow_large_totals() {
+        let vesting = schedule(5_000, 0, i64::MAX);
+        
+        assert_eq!(vesting.vested_amount(u64::MAX, 0), u64::MAX / 2);
+        assert!(vesting.vested_amount(u64::MAX, i64::MAX - 1) < u64::MAX);
+        assert_eq!(vesting.vested_amount(u64::MAX, i64::MAX), u64::MAX);
+    }
+    
+    #[test]
+    fn status_transition_table() {
+        use PresaleStatus::*;
+        
+        let statuses = [Pending, Active, Succeeded, Failed, Finalized, Cancelled];
+        let allowed = [
+            (Pending, Active),
+            (Pending, Cancelled),
+            (Active, Succeeded),
+            (Active, Failed),
+            (Active, Cancelled),
+            (Succeeded, Finalized),
+            (Succeeded, Cancelled),
+        ];
+        
+        for from in statuses {
+            for to in statuses {
+                assert_eq!(
+                    from.can_transition_to(to),
+                    allowed.contains(&(from, to)),
+                    "{:?} -> {:?}",
+                    from,
+                    to
+                );
+            }
+        }
+        
+        // Failed, Finalized and Cancelled are terminal
+        for terminal in [Failed, Finalized, Cancelled] {
+            assert!(statuses.iter().all(|next| !terminal.can_transition_to(*next)));
+        }
+    }
+    
+    #[test]
+    fn refresh_status_resolves_on_time_and_hard_cap() {
+        let mut state = PresaleState {
+            start_time: 100,
+            end_time: 200,
+            soft_cap: 50,
+            hard_cap: 100,
+            ..Default::default()
+        };
+        
+        state.refresh_status(99).unwrap();
+        assert_eq!(state.status, PresaleStatus::Pending);
+        state.refresh_status(100).unwrap();
+        assert_eq!(state.status, PresaleStatus::Active);
+        state.refresh_status(200).unwrap();
+        assert_eq!(state.status, PresaleStatus::Active);
+        
+        let mut failed = state.clone();
+        failed.total_contributions = 49;
+        failed.refresh_status(201).unwrap();
+        assert_eq!(failed.status, PresaleStatus::Failed);
+        
+        let mut succeeded = state.clone();
+        succeeded.total_contributions = 50;
+        succeeded.refresh_status(201).unwrap();
+        assert_eq!(succeeded.status, PresaleStatus::Succeeded);
+        
+        // Filling the hard cap ends the sale early
+        let mut filled = state.clone();
+        filled.total_contributions = 100;
+        filled.refresh_status(150).unwrap();
+        assert_eq!(filled.status, PresaleStatus::Succeeded);
+        
+        // Terminal states are left alone
+        for terminal in [PresaleStatus::Finalized, PresaleStatus::Cancelled, PresaleStatus::Failed] {
+            let mut resolved = state.clone();
+            resolved.status = terminal;
+            resolved.refresh_status(1_000).unwrap();
+            assert_eq!(resolved.status, terminal);
+        }
+    }
+    
+    #[test]
+    fn transition_rejects_forbidden_edges() {
+        let mut state = PresaleState {
+            status: PresaleStatus::Finalized,
+            ..Default::default()
+        };
+        
+        assert!(state.transition(PresaleStatus::Cancelled).is_err());
+        assert_eq!(state.status, PresaleStatus::Finalized);
+    }
+    
+    fn lockup_record() -> LockupRecord {
+        LockupRecord {
+            owner: Pubkey::new_unique(),
+            token_account: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            tranches: vec![],
+        }
+    }
+    
+    #[test]
+    fn lockup_tranches_unlock_independently() {
+        let first_round = Pubkey::new_unique();
+        let second_round = Pubkey::new_unique();
+        let mut lockup = lockup_record();
+        
+        lockup.lock(first_round, 100, 1_000, 0).unwrap();
+        lockup.lock(second_round, 50, 5_000, 500).unwrap();
+        
+        assert_eq!(lockup.locked_amount(500), 150);
+        // The later round does not extend the earlier round's lock
+        assert_eq!(lockup.locked_amount(1_000), 50);
+        assert_eq!(lockup.locked_amount(5_000), 0);
+    }
+    
+    #[test]
+    fn lockup_claims_from_one_presale_share_a_tranche() {
+        let presale = Pubkey::new_unique();
+        let mut lockup = lockup_record();
+        
+        lockup.lock(presale, 100, 1_000, 0).unwrap();
+        lockup.lock(presale, 25, 1_000, 10).unwrap();
+        
+        assert_eq!(lockup.tranches.len(), 1);
+        assert_eq!(lockup.locked_amount(10), 125);
+    }
+    
+    #[test]
+    fn lockup_drops_unlocked_tranches_before_capping() {
+        let mut lockup = lockup_record();
+        for _ in 0..MAX_LOCKUP_TRANCHES {
+            lockup.lock(Pubkey::new_unique(), 1, 100, 0).unwrap();
+        }
+        
+        assert!(lockup.lock(Pubkey::new_unique(), 1, 200, 50).is_err());
+        
+        // Once the earlier tranches unlock they make room again
+        lockup.lock(Pubkey::new_unique(), 1, 200, 100).unwrap();
+        assert_eq!(lockup.tranches.len(), 1);
+    }
+    
+    #[test]
+    fn lockup_record_size_fits_max_tranches() {
+        let tranche = LockupTranche {
+            presale: Pubkey::new_unique(),
+            amount: u64::MAX,
+            unlock_time: i64::MAX,
+        };
+        let lockup = LockupRecord {
+            tranches: vec![tranche; MAX_LOCKUP_TRANCHES],
+            ..lockup_record()
+        };
+        
+        assert_eq!(lockup.try_to_vec().unwrap().len(), LockupRecord::SIZE);
+    }
 }
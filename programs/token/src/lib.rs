@@ -1,9 +1,28 @@
+// Instruction handlers mirror their on-chain argument lists
+#![allow(clippy::too_many_arguments)]
+
 use anchor_lang::prelude::*;
 use anchor_lang::solana_program::account_info::AccountInfo;
-use anchor_spl::token_interface::{self, InitializeMint2, MintTo, Burn, TransferChecked, Approve};
-use anchor_spl::associated_token::{self, Create};
+use anchor_lang::solana_program::instruction::AccountMeta;
+use anchor_lang::solana_program::program::invoke;
+use anchor_lang::system_program::{self, CreateAccount, Transfer};
+use anchor_spl::token_interface::{self, InitializeMint2, MintTo, Burn, Approve, Revoke};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use anchor_spl::token_2022::Token2022;
+use anchor_spl::token_interface::{MetadataPointerInitialize, TokenMetadataInitialize, TokenMetadataUpdateField};
+use anchor_spl::token_interface::{TransferFeeInitialize, TransferFeeSetTransferFee, HarvestWithheldTokensToMint, WithdrawWithheldTokensFromMint};
+use anchor_spl::token_interface::TransferHookInitialize;
+use anchor_spl::token_interface::{DefaultAccountStateInitialize, FreezeAccount, ThawAccount, SetAuthority};
+use anchor_spl::token_interface::spl_pod::optional_keys::OptionalNonZeroPubkey;
+use anchor_spl::token_interface::spl_token_metadata_interface::state::{Field, TokenMetadata};
+use anchor_spl::token_2022::spl_token_2022;
+use anchor_spl::token_2022::spl_token_2022::extension::ExtensionType;
+use anchor_spl::token_2022::spl_token_2022::extension::transfer_fee::TransferFeeConfig;
+use anchor_spl::token_2022::spl_token_2022::state::AccountState;
+use anchor_spl::token_2022::spl_token_2022::instruction::AuthorityType;
+use anchor_spl::associated_token::{self, AssociatedToken, Create};
 
-declare_id!("your_program_pubkey_in_base58");
+declare_id!("78dsCY6k86qfHbEtrmHw3smjBce2k6qhCjdetnpBCUbJ");
 
 #[program]
 pub mod token {
@@ -16,24 +35,159 @@ pub mod token {
         uri: String,
         decimals: u8,
         supply: u64,
+        additional_metadata: Vec<MetadataEntry>,
+        transfer_fee: Option<TransferFeeArgs>,
+        transfer_hook_program_id: Option<Pubkey>,
+        default_account_frozen: bool,
+        freeze_authority: Option<Pubkey>,
+        pda_mint_authority: bool,
+        max_supply: Option<u64>,
     ) -> Result<()> {
         msg!("Initializing ZL Token (zerolayers) with Token Interface");
         
+        // A wallet mint authority could mint around the cap straight through Token-2022
+        require!(
+            max_supply.is_none() || pda_mint_authority,
+            MyError::MaxSupplyRequiresPdaMintAuthority
+        );
+        if let Some(max_supply) = max_supply {
+            require!(supply <= max_supply, MyError::MaxSupplyExceeded);
+        }
+        // Default-frozen accounts could never be thawed without one
+        require!(
+            freeze_authority.is_some() || !default_account_frozen,
+            MyError::FreezeAuthorityRequired
+        );
+        
         let token_state = &mut ctx.accounts.token_state;
         token_state.authority = ctx.accounts.authority.key();
-        token_state.mint = *ctx.accounts.mint.key;
-        token_state.name = name;
-        token_state.symbol = symbol;
-        token_state.uri = uri;
+        token_state.mint = ctx.accounts.mint.key();
         token_state.decimals = decimals;
         token_state.total_supply = supply;
+        token_state.freeze_authority = freeze_authority;
+        token_state.pending_authority = None;
+        token_state.mint_authority = Some(ctx.accounts.authority.key());
+        token_state.max_supply = max_supply;
+        
+        // The mint is created at the size of its fixed extensions; the metadata
+        // TLV entry is reallocated by Token-2022, so fund its rent up front
+        let mut extensions = vec![ExtensionType::MetadataPointer];
+        if transfer_fee.is_some() {
+            extensions.push(ExtensionType::TransferFeeConfig);
+        }
+        if transfer_hook_program_id.is_some() {
+            extensions.push(ExtensionType::TransferHook);
+        }
+        if default_account_frozen {
+            extensions.push(ExtensionType::DefaultAccountState);
+        }
+        let mint_space = anchor_spl::token_interface::find_mint_account_size(Some(&extensions))?;
+        let token_metadata = TokenMetadata {
+            update_authority: OptionalNonZeroPubkey::try_from(Some(ctx.accounts.extension_authority.key()))?,
+            mint: ctx.accounts.mint.key(),
+            name: name.clone(),
+            symbol: symbol.clone(),
+            uri: uri.clone(),
+            additional_metadata: additional_metadata
+                .iter()
+                .map(|entry| (entry.key.clone(), entry.value.clone()))
+                .collect(),
+        };
+        let metadata_space = token_metadata.tlv_size_of()?;
+        
+        let mint_key = ctx.accounts.mint.key();
+        let extension_authority_seeds = &[
+            b"extension_authority".as_ref(),
+            mint_key.as_ref(),
+            &[ctx.bumps.extension_authority],
+        ];
+        let extension_authority_signer = &[&extension_authority_seeds[..]];
+        let lamports = ctx.accounts.rent.minimum_balance(mint_space + metadata_space);
+        
+        let cpi_accounts_create = CreateAccount {
+            from: ctx.accounts.authority.to_account_info(),
+            to: ctx.accounts.mint.to_account_info(),
+        };
+        let cpi_ctx_create = CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts_create);
+        
+        system_program::create_account(
+            cpi_ctx_create,
+            lamports,
+            mint_space as u64,
+            &ctx.accounts.token_program.key(),
+        )?;
+        
+        let cpi_accounts_pointer = MetadataPointerInitialize {
+            token_program_id: ctx.accounts.token_program.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
+        };
+        let cpi_ctx_pointer = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts_pointer);
+        
+        token_interface::metadata_pointer_initialize(
+            cpi_ctx_pointer,
+            Some(ctx.accounts.extension_authority.key()),
+            Some(ctx.accounts.mint.key()),
+        )?;
+        
+        if let Some(transfer_fee) = &transfer_fee {
+            require!(
+                transfer_fee.basis_points <= MAX_FEE_BASIS_POINTS,
+                MyError::InvalidTransferFee
+            );
+            
+            let cpi_accounts_fee = TransferFeeInitialize {
+                token_program_id: ctx.accounts.token_program.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+            };
+            let cpi_ctx_fee = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts_fee);
+            
+            token_interface::transfer_fee_initialize(
+                cpi_ctx_fee,
+                Some(&ctx.accounts.extension_authority.key()),
+                Some(&ctx.accounts.extension_authority.key()),
+                transfer_fee.basis_points,
+                transfer_fee.maximum_fee,
+            )?;
+            
+            msg!("Transfer fee set to {} bps, capped at {}", transfer_fee.basis_points, transfer_fee.maximum_fee);
+        }
+        
+        if let Some(transfer_hook_program_id) = transfer_hook_program_id {
+            let cpi_accounts_hook = TransferHookInitialize {
+                token_program_id: ctx.accounts.token_program.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+            };
+            let cpi_ctx_hook = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts_hook);
+            
+            token_interface::transfer_hook_initialize(
+                cpi_ctx_hook,
+                Some(ctx.accounts.extension_authority.key()),
+                Some(transfer_hook_program_id),
+            )?;
+            
+            msg!("Transfers routed through hook program {}", transfer_hook_program_id);
+        }
+        
+        if default_account_frozen {
+            let cpi_accounts_state = DefaultAccountStateInitialize {
+                token_program_id: ctx.accounts.token_program.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+            };
+            let cpi_ctx_state = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts_state);
+            
+            token_interface::default_account_state_initialize(cpi_ctx_state, &AccountState::Frozen)?;
+            
+            msg!("New token accounts start frozen");
+        }
         
         let cpi_accounts_init = InitializeMint2 {
-            mint: ctx.accounts.mint.clone(),
+            mint: ctx.accounts.mint.to_account_info(),
         };
-        let cpi_program = ctx.accounts.token_program.clone();
+        let cpi_program = ctx.accounts.token_program.to_account_info();
         let cpi_ctx_init = CpiContext::new(cpi_program.clone(), cpi_accounts_init);
         
+        // The authority holds the freeze authority until its own account is
+        // thawed below, then hands it to `freeze_authority`
         token_interface::initialize_mint2(
             cpi_ctx_init,
             token_state.decimals,
@@ -43,105 +197,297 @@ pub mod token {
         
         msg!("Mint initialized with {} decimals", decimals);
         
+        let cpi_accounts_metadata = TokenMetadataInitialize {
+            token_program_id: ctx.accounts.token_program.to_account_info(),
+            metadata: ctx.accounts.mint.to_account_info(),
+            update_authority: ctx.accounts.extension_authority.to_account_info(),
+            mint_authority: ctx.accounts.authority.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
+        };
+        let cpi_ctx_metadata = CpiContext::new(cpi_program.clone(), cpi_accounts_metadata);
+        
+        token_interface::token_metadata_initialize(cpi_ctx_metadata, name, symbol, uri)?;
+        
+        for entry in additional_metadata {
+            let cpi_accounts_field = TokenMetadataUpdateField {
+                token_program_id: ctx.accounts.token_program.to_account_info(),
+                metadata: ctx.accounts.mint.to_account_info(),
+                update_authority: ctx.accounts.extension_authority.to_account_info(),
+            };
+            let cpi_ctx_field = CpiContext::new_with_signer(cpi_program.clone(), cpi_accounts_field, extension_authority_signer);
+            
+            token_interface::token_metadata_update_field(cpi_ctx_field, Field::Key(entry.key), entry.value)?;
+        }
+        
+        msg!("Token metadata written to the mint");
+        
         let cpi_accounts_ata = Create {
             payer: ctx.accounts.authority.to_account_info(),
-            associated_token: ctx.accounts.token_account.clone(),
+            associated_token: ctx.accounts.token_account.to_account_info(),
             authority: ctx.accounts.authority.to_account_info(),
-            mint: ctx.accounts.mint.clone(),
+            mint: ctx.accounts.mint.to_account_info(),
             system_program: ctx.accounts.system_program.to_account_info(),
-            token_program: ctx.accounts.token_program.clone(),
+            token_program: ctx.accounts.token_program.to_account_info(),
         };
         
-        let cpi_program_ata = ctx.accounts.associated_token_program.clone();
+        let cpi_program_ata = ctx.accounts.associated_token_program.to_account_info();
         let cpi_ctx_ata = CpiContext::new(cpi_program_ata, cpi_accounts_ata);
         
         associated_token::create(cpi_ctx_ata)?;
         
         msg!("Associated Token Account created");
         
+        // The authority's account starts frozen too; thaw it to receive the initial supply
+        if default_account_frozen {
+            let cpi_accounts_thaw = ThawAccount {
+                account: ctx.accounts.token_account.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                authority: ctx.accounts.authority.to_account_info(),
+            };
+            let cpi_ctx_thaw = CpiContext::new(cpi_program.clone(), cpi_accounts_thaw);
+            
+            token_interface::thaw_account(cpi_ctx_thaw)?;
+        }
+        
         let cpi_accounts_mint = MintTo {
-            mint: ctx.accounts.mint.clone(),
-            to: ctx.accounts.token_account.clone(),
+            mint: ctx.accounts.mint.to_account_info(),
+            to: ctx.accounts.token_account.to_account_info(),
             authority: ctx.accounts.authority.to_account_info(),
         };
         
-        let cpi_ctx_mint = CpiContext::new(cpi_program, cpi_accounts_mint);
+        let cpi_ctx_mint = CpiContext::new(cpi_program.clone(), cpi_accounts_mint);
         
         token_interface::mint_to(cpi_ctx_mint, token_state.total_supply)?;
         
         msg!("Successfully minted {} tokens", token_state.total_supply);
         
+        emit!(TokensMinted {
+            mint: ctx.accounts.mint.key(),
+            authority: ctx.accounts.authority.key(),
+            destination: ctx.accounts.token_account.key(),
+            amount: token_state.total_supply,
+            total_supply: token_state.total_supply,
+        });
+        
+        if freeze_authority != Some(ctx.accounts.authority.key()) {
+            let cpi_accounts_freeze = SetAuthority {
+                current_authority: ctx.accounts.authority.to_account_info(),
+                account_or_mint: ctx.accounts.mint.to_account_info(),
+            };
+            let cpi_ctx_freeze = CpiContext::new(cpi_program.clone(), cpi_accounts_freeze);
+            
+            token_interface::set_authority(cpi_ctx_freeze, AuthorityType::FreezeAccount, freeze_authority)?;
+            
+            match freeze_authority {
+                Some(freeze_authority) => msg!("Freeze authority set to {}", freeze_authority),
+                None => msg!("Mint created without a freeze authority"),
+            }
+        }
+        
+        // Hand minting to a program PDA once the initial supply is out
+        if pda_mint_authority {
+            let (mint_authority, _) = Pubkey::find_program_address(
+                &[b"mint_authority", ctx.accounts.mint.key().as_ref()],
+                ctx.program_id,
+            );
+            
+            let cpi_accounts_authority = SetAuthority {
+                current_authority: ctx.accounts.authority.to_account_info(),
+                account_or_mint: ctx.accounts.mint.to_account_info(),
+            };
+            let cpi_ctx_authority = CpiContext::new(cpi_program, cpi_accounts_authority);
+            
+            token_interface::set_authority(cpi_ctx_authority, AuthorityType::MintTokens, Some(mint_authority))?;
+            
+            token_state.mint_authority = Some(mint_authority);
+            
+            msg!("Mint authority moved to PDA {}", mint_authority);
+        }
+        
+        Ok(())
+    }
+    
+    pub fn update_metadata(
+        ctx: Context<UpdateMetadata>,
+        field: MetadataField,
+        value: String,
+    ) -> Result<()> {
+        let token_state = &ctx.accounts.token_state;
+        
+        require!(
+            ctx.accounts.authority.key() == token_state.authority,
+            MyError::UnauthorizedMetadataAuthority
+        );
+        
+        let mint_key = ctx.accounts.mint.key();
+        let seeds = &[
+            b"extension_authority".as_ref(),
+            mint_key.as_ref(),
+            &[ctx.bumps.extension_authority],
+        ];
+        let signer = &[&seeds[..]];
+        
+        let cpi_accounts = TokenMetadataUpdateField {
+            token_program_id: ctx.accounts.token_program.to_account_info(),
+            metadata: ctx.accounts.mint.to_account_info(),
+            update_authority: ctx.accounts.extension_authority.to_account_info(),
+        };
+        
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        
+        token_interface::token_metadata_update_field(cpi_ctx, field.into(), value)?;
+        
+        // Token-2022 reallocates the mint to fit the new value; keep it rent-exempt
+        let required_lamports = Rent::get()?.minimum_balance(ctx.accounts.mint.to_account_info().data_len());
+        let missing_lamports = required_lamports.saturating_sub(ctx.accounts.mint.to_account_info().lamports());
+        if missing_lamports > 0 {
+            let cpi_accounts_rent = Transfer {
+                from: ctx.accounts.authority.to_account_info(),
+                to: ctx.accounts.mint.to_account_info(),
+            };
+            let cpi_ctx_rent = CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts_rent);
+            
+            system_program::transfer(cpi_ctx_rent, missing_lamports)?;
+        }
+        
+        msg!("Successfully updated token metadata");
+        
         Ok(())
     }
 
+    /// Mints new tokens.
+    ///
+    /// When the mint authority is the program PDA, pass it as `mint_authority`;
+    /// the signer must then be the token authority or a registered minter,
+    /// whose `minter` account tracks its allowance. Otherwise the signer must
+    /// be the mint authority itself.
     pub fn mint_tokens(
         ctx: Context<MintTokens>,
         amount: u64,
     ) -> Result<()> {
         let token_state = &ctx.accounts.token_state;
         
-        require!(
-            ctx.accounts.authority.key() == token_state.authority,
-            MyError::UnauthorizedMintAuthority
-        );
+        if let Some(mint_authority) = &ctx.accounts.mint_authority {
+            require!(
+                Some(mint_authority.key()) == token_state.mint_authority,
+                MyError::UnauthorizedMintAuthority
+            );
+            
+            if ctx.accounts.authority.key() != token_state.authority {
+                let minter = ctx.accounts.minter.as_mut().ok_or(MyError::UnauthorizedMintAuthority)?;
+                minter.minted = minter.minted.checked_add(amount).ok_or(MyError::OverflowError)?;
+                require!(minter.minted <= minter.allowance, MyError::MinterAllowanceExceeded);
+                
+                msg!("Minter {} has used {} of {}", minter.minter, minter.minted, minter.allowance);
+            }
+        } else {
+            require!(
+                Some(ctx.accounts.authority.key()) == token_state.mint_authority,
+                MyError::UnauthorizedMintAuthority
+            );
+        }
 
-        let token_state = ctx.accounts.token_state.to_account_info();
-        let mut token_state_data = token_state.try_borrow_mut_data()?;
-        let mut token_state_account = TokenState::try_deserialize(&mut &token_state_data[..])?;
-        token_state_account.total_supply = token_state_account.total_supply.checked_add(amount).ok_or(MyError::OverflowError)?;
-        token_state_account.try_serialize(&mut *token_state_data)?;
+        // Start from the mint's supply so burns made outside the program count
+        let token_state = &mut ctx.accounts.token_state;
+        let total_supply = ctx.accounts.mint.supply.checked_add(amount).ok_or(MyError::OverflowError)?;
+        if let Some(max_supply) = token_state.max_supply {
+            require!(total_supply <= max_supply, MyError::MaxSupplyExceeded);
+        }
+        token_state.total_supply = total_supply;
 
-        let cpi_accounts = MintTo {
-            mint: ctx.accounts.mint.clone(),
-            to: ctx.accounts.token_account.clone(),
-            authority: ctx.accounts.authority.to_account_info(),
-        };
-        
-        let cpi_program = ctx.accounts.token_program.clone();
-        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        let cpi_program = ctx.accounts.token_program.to_account_info();
         
-        token_interface::mint_to(cpi_ctx, amount)?;
+        if let Some(mint_authority) = &ctx.accounts.mint_authority {
+            let mint_key = ctx.accounts.mint.key();
+            let seeds = &[
+                b"mint_authority".as_ref(),
+                mint_key.as_ref(),
+                &[ctx.bumps.mint_authority.ok_or(MyError::UnauthorizedMintAuthority)?],
+            ];
+            let signer = &[&seeds[..]];
+            
+            let cpi_accounts = MintTo {
+                mint: ctx.accounts.mint.to_account_info(),
+                to: ctx.accounts.token_account.to_account_info(),
+                authority: mint_authority.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+            
+            token_interface::mint_to(cpi_ctx, amount)?;
+        } else {
+            let cpi_accounts = MintTo {
+                mint: ctx.accounts.mint.to_account_info(),
+                to: ctx.accounts.token_account.to_account_info(),
+                authority: ctx.accounts.authority.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+            
+            token_interface::mint_to(cpi_ctx, amount)?;
+        }
         
         msg!("Successfully minted {} additional tokens", amount);
         
+        emit!(TokensMinted {
+            mint: ctx.accounts.mint.key(),
+            authority: ctx.accounts.authority.key(),
+            destination: ctx.accounts.token_account.key(),
+            amount,
+            total_supply: ctx.accounts.token_state.total_supply,
+        });
+        
         Ok(())
     }
 
+    /// Burns tokens from the signer's own token account, or from an account
+    /// that has approved the signer as delegate for at least `amount`.
     pub fn burn_tokens(
         ctx: Context<BurnTokens>,
         amount: u64,
     ) -> Result<()> {
-        let token_state = &ctx.accounts.token_state;
+        let token_account = &ctx.accounts.token_account;
+        let signer = ctx.accounts.owner.key();
         
         require!(
-            ctx.accounts.owner.key() == token_state.authority,
+            token_account.owner == signer
+                || (token_account.delegate == Some(signer).into() && token_account.delegated_amount >= amount),
             MyError::UnauthorizedBurnAuthority
         );
 
-        let token_state = ctx.accounts.token_state.to_account_info();
-        let mut token_state_data = token_state.try_borrow_mut_data()?;
-        let mut token_state_account = TokenState::try_deserialize(&mut &token_state_data[..])?;
-        token_state_account.total_supply = token_state_account.total_supply.checked_sub(amount).ok_or(MyError::UnderflowError)?;
-        token_state_account.try_serialize(&mut *token_state_data)?;
-
         let cpi_accounts = Burn {
-            mint: ctx.accounts.mint.clone(),
-            from: ctx.accounts.token_account.clone(),
+            mint: ctx.accounts.mint.to_account_info(),
+            from: ctx.accounts.token_account.to_account_info(),
             authority: ctx.accounts.owner.to_account_info(),
         };
         
-        let cpi_program = ctx.accounts.token_program.clone();
+        let cpi_program = ctx.accounts.token_program.to_account_info();
         let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
         
         token_interface::burn(cpi_ctx, amount)?;
         
-        msg!("Successfully burned {} tokens", amount);
+        ctx.accounts.mint.reload()?;
+        let token_state = &mut ctx.accounts.token_state;
+        token_state.total_supply = ctx.accounts.mint.supply;
+        
+        msg!("Successfully burned {} tokens, supply is now {}", amount, token_state.total_supply);
+        
+        emit!(TokensBurned {
+            mint: ctx.accounts.mint.key(),
+            authority: signer,
+            source: ctx.accounts.token_account.key(),
+            amount,
+            total_supply: token_state.total_supply,
+        });
         
         Ok(())
     }
 
-    pub fn transfer_tokens(
-        ctx: Context<TransferTokens>,
+    /// Transfers tokens with `transfer_checked`, or the fee-aware variant for
+    /// mints with a transfer fee.
+    ///
+    /// Hooked mints take remaining accounts; see `presale_client::transfer_hook_accounts`.
+    pub fn transfer_tokens<'info>(
+        ctx: Context<'_, '_, '_, 'info, TransferTokens<'info>>,
         amount: u64,
     ) -> Result<()> {
         msg!("Transferring {} tokens", amount);
@@ -149,159 +495,1005 @@ pub mod token {
         let token_state = &ctx.accounts.token_state;
         let decimals = token_state.decimals;
         
-        let cpi_accounts = TransferChecked {
-            from: ctx.accounts.from.clone(),
-            to: ctx.accounts.to.clone(),
-            authority: ctx.accounts.owner.to_account_info(),
-            mint: ctx.accounts.mint.clone(),
+        // Mints with a transfer fee must go through the fee-aware instruction
+        let fee = match token_interface::get_mint_extension_data::<TransferFeeConfig>(&ctx.accounts.mint.to_account_info()) {
+            Ok(fee_config) => Some(
+                fee_config
+                    .calculate_epoch_fee(Clock::get()?.epoch, amount)
+                    .ok_or(MyError::OverflowError)?,
+            ),
+            Err(_) => None,
         };
         
-        let cpi_program = ctx.accounts.token_program.clone();
-        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-        
-        token_interface::transfer_checked(cpi_ctx, amount, decimals)?;
+        if let Some(fee) = fee {
+            let mut ix = spl_token_2022::extension::transfer_fee::instruction::transfer_checked_with_fee(
+                &ctx.accounts.token_program.key(),
+                &ctx.accounts.from.key(),
+                &ctx.accounts.mint.key(),
+                &ctx.accounts.to.key(),
+                ctx.accounts.owner.key,
+                &[],
+                amount,
+                decimals,
+                fee,
+            )?;
+            let mut account_infos = vec![
+                ctx.accounts.from.to_account_info(),
+                ctx.accounts.mint.to_account_info(),
+                ctx.accounts.to.to_account_info(),
+                ctx.accounts.owner.to_account_info(),
+            ];
+            
+            // Token-2022 resolves the transfer-hook accounts from whatever follows the transfer accounts
+            for account in ctx.remaining_accounts {
+                ix.accounts.push(AccountMeta {
+                    pubkey: *account.key,
+                    is_signer: account.is_signer,
+                    is_writable: account.is_writable,
+                });
+                account_infos.push(account.clone());
+            }
+            
+            invoke(&ix, &account_infos)?;
+            
+            msg!("Transfer fee withheld: {} tokens", fee);
+        } else {
+            spl_token_2022::onchain::invoke_transfer_checked(
+                &ctx.accounts.token_program.key(),
+                ctx.accounts.from.to_account_info(),
+                ctx.accounts.mint.to_account_info(),
+                ctx.accounts.to.to_account_info(),
+                ctx.accounts.owner.to_account_info(),
+                ctx.remaining_accounts,
+                amount,
+                decimals,
+                &[],
+            )?;
+        }
         
         msg!("Successfully transferred {} tokens", amount);
         
+        emit!(TokensTransferred {
+            mint: ctx.accounts.mint.key(),
+            authority: ctx.accounts.owner.key(),
+            source: ctx.accounts.from.key(),
+            destination: ctx.accounts.to.key(),
+            amount,
+            fee: fee.unwrap_or(0),
+        });
+        
         Ok(())
     }
 
-    pub fn approve_tokens(
-        ctx: Context<ApproveTokens>,
-        amount: u64,
+    pub fn harvest_withheld_fees<'info>(
+        ctx: Context<'_, '_, '_, 'info, HarvestWithheldFees<'info>>,
     ) -> Result<()> {
-        msg!("Approving {} tokens for delegate", amount);
+        let sources = ctx.remaining_accounts.to_vec();
+        let source_count = sources.len();
         
-        let cpi_accounts = Approve {
-            to: ctx.accounts.token_account.clone(),
-            delegate: ctx.accounts.delegate.clone(),
-            authority: ctx.accounts.owner.to_account_info(),
+        let cpi_accounts = HarvestWithheldTokensToMint {
+            token_program_id: ctx.accounts.token_program.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
         };
         
-        let cpi_program = ctx.accounts.token_program.clone();
+        let cpi_program = ctx.accounts.token_program.to_account_info();
         let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
         
-        token_interface::approve(cpi_ctx, amount)?;
+        token_interface::harvest_withheld_tokens_to_mint(cpi_ctx, sources)?;
         
-        msg!("Successfully approved {} tokens for delegate", amount);
+        msg!("Harvested withheld fees from {} token accounts to the mint", source_count);
         
         Ok(())
     }
-}
-
-#[account]
-#[derive(Default)]
-pub struct TokenState {
-    pub authority: Pubkey,    // 32 bytes
-    pub mint: Pubkey,         // 32 bytes
-    pub name: String,         // varies
-    pub symbol: String,       // varies
-    pub uri: String,          // varies
-    pub decimals: u8,         // 1 byte
-    pub total_supply: u64,    // 8 bytes
-}
-
-#[derive(Accounts)]
-pub struct InitializeToken<'info> {
-    #[account(mut)]
-    pub authority: Signer<'info>,
     
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + 32 + 32 + 4 + 50 + 4 + 10 + 4 + 200 + 1 + 8
-    )]
-    pub token_state: Account<'info, TokenState>,
+    pub fn withdraw_withheld_fees(ctx: Context<WithdrawWithheldFees>) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.token_state.authority,
+            MyError::UnauthorizedAuthority
+        );
+        
+        let mint_key = ctx.accounts.mint.key();
+        let seeds = &[
+            b"extension_authority".as_ref(),
+            mint_key.as_ref(),
+            &[ctx.bumps.extension_authority],
+        ];
+        let signer = &[&seeds[..]];
+        
+        let cpi_accounts = WithdrawWithheldTokensFromMint {
+            token_program_id: ctx.accounts.token_program.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
+            destination: ctx.accounts.destination.to_account_info(),
+            authority: ctx.accounts.extension_authority.to_account_info(),
+        };
+        
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        
+        token_interface::withdraw_withheld_tokens_from_mint(cpi_ctx)?;
+        
+        msg!("Withdrew withheld fees to {}", ctx.accounts.destination.key());
+        
+        Ok(())
+    }
     
-    /// CHECK: This is the mint account that is initialized in the instruction
-    #[account(
-        init,
-        payer = authority,
-        space = 82,  // Minimum space for a mint account
-        owner = token_program.key()
-    )]
-    pub mint: AccountInfo<'info>,
+    /// Changes the transfer fee. Token-2022 applies it two epochs later.
+    pub fn set_transfer_fee(
+        ctx: Context<SetTransferFee>,
+        basis_points: u16,
+        maximum_fee: u64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.token_state.authority,
+            MyError::UnauthorizedAuthority
+        );
+        require!(basis_points <= MAX_FEE_BASIS_POINTS, MyError::InvalidTransferFee);
+        
+        let mint_key = ctx.accounts.mint.key();
+        let seeds = &[
+            b"extension_authority".as_ref(),
+            mint_key.as_ref(),
+            &[ctx.bumps.extension_authority],
+        ];
+        let signer = &[&seeds[..]];
+        
+        let cpi_accounts = TransferFeeSetTransferFee {
+            token_program_id: ctx.accounts.token_program.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
+            authority: ctx.accounts.extension_authority.to_account_info(),
+        };
+        
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        
+        token_interface::transfer_fee_set(cpi_ctx, basis_points, maximum_fee)?;
+        
+        msg!("Transfer fee set to {} bps, capped at {}", basis_points, maximum_fee);
+        
+        Ok(())
+    }
     
-    /// CHECK: This is the token account that is initialized in the instruction
-    #[account(mut)]
-    pub token_account: AccountInfo<'info>,
+    pub fn freeze_account(ctx: Context<FreezeTokenAccount>) -> Result<()> {
+        require!(
+            Some(ctx.accounts.freeze_authority.key()) == ctx.accounts.token_state.freeze_authority,
+            MyError::UnauthorizedFreezeAuthority
+        );
+        
+        let cpi_accounts = FreezeAccount {
+            account: ctx.accounts.token_account.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
+            authority: ctx.accounts.freeze_authority.to_account_info(),
+        };
+        
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        
+        token_interface::freeze_account(cpi_ctx)?;
+        
+        msg!("Froze token account {}", ctx.accounts.token_account.key());
+        
+        Ok(())
+    }
     
-    /// CHECK: This is the token program ID
-    pub token_program: AccountInfo<'info>,
+    pub fn thaw_account(ctx: Context<ThawTokenAccount>) -> Result<()> {
+        require!(
+            Some(ctx.accounts.freeze_authority.key()) == ctx.accounts.token_state.freeze_authority,
+            MyError::UnauthorizedFreezeAuthority
+        );
+        
+        let cpi_accounts = ThawAccount {
+            account: ctx.accounts.token_account.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
+            authority: ctx.accounts.freeze_authority.to_account_info(),
+        };
+        
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        
+        token_interface::thaw_account(cpi_ctx)?;
+        
+        msg!("Thawed token account {}", ctx.accounts.token_account.key());
+        
+        Ok(())
+    }
     
-    /// CHECK: This is the associated token program ID
-    pub associated_token_program: AccountInfo<'info>,
+    /// Lowers the supply cap, or sets one on an uncapped PDA-controlled mint.
+    /// The cap can never be raised, nor set below the mint's current supply.
+    pub fn decrease_max_supply(
+        ctx: Context<DecreaseMaxSupply>,
+        new_max_supply: u64,
+    ) -> Result<()> {
+        let (mint_authority, _) = Pubkey::find_program_address(
+            &[b"mint_authority", ctx.accounts.mint.key().as_ref()],
+            ctx.program_id,
+        );
+        let token_state = &mut ctx.accounts.token_state;
+        
+        require!(
+            ctx.accounts.authority.key() == token_state.authority,
+            MyError::UnauthorizedAuthority
+        );
+        require!(
+            token_state.mint_authority == Some(mint_authority),
+            MyError::MaxSupplyRequiresPdaMintAuthority
+        );
+        require!(
+            new_max_supply <= token_state.max_supply.unwrap_or(u64::MAX)
+                && new_max_supply >= ctx.accounts.mint.supply,
+            MyError::InvalidMaxSupply
+        );
+        
+        token_state.max_supply = Some(new_max_supply);
+        
+        msg!("Maximum supply lowered to {}", new_max_supply);
+        
+        Ok(())
+    }
     
-    pub system_program: Program<'info, System>,
-    pub rent: Sysvar<'info, Rent>,
-}
-
-#[derive(Accounts)]
-pub struct MintTokens<'info> {
-    #[account(mut)]
-    pub authority: Signer<'info>,
+    /// Registers a minter, or updates its allowance, for a PDA-controlled mint.
+    /// `allowance` caps the total the minter may ever mint.
+    pub fn set_minter(
+        ctx: Context<SetMinter>,
+        minter: Pubkey,
+        allowance: u64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.token_state.authority,
+            MyError::UnauthorizedAuthority
+        );
+        
+        let minter_account = &mut ctx.accounts.minter_account;
+        minter_account.token_state = ctx.accounts.token_state.key();
+        minter_account.minter = minter;
+        minter_account.allowance = allowance;
+        
+        msg!("Minter {} allowed to mint {} tokens ({} already minted)", minter, allowance, minter_account.minted);
+        
+        Ok(())
+    }
     
-    #[account(mut)]
-    pub token_state: Account<'info, TokenState>,
+    pub fn remove_minter(ctx: Context<RemoveMinter>) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.token_state.authority,
+            MyError::UnauthorizedAuthority
+        );
+        
+        msg!("Removed minter {}", ctx.accounts.minter_account.minter);
+        
+        Ok(())
+    }
     
-    /// CHECK: This is the mint account
-    #[account(
-        mut,
-        constraint = token_state.mint == *mint.key
-    )]
-    pub mint: AccountInfo<'info>,
+    /// First step of a `TokenState` authority transfer; the new authority
+    /// takes over once it calls `accept_authority`.
+    ///
+    /// The mint's metadata, metadata-pointer, transfer-fee and transfer-hook
+    /// authorities are held by the program's extension authority PDA and
+    /// exercised on behalf of `token_state.authority`, so they move with it.
+    pub fn propose_authority(
+        ctx: Context<ProposeAuthority>,
+        new_authority: Pubkey,
+    ) -> Result<()> {
+        let token_state = &mut ctx.accounts.token_state;
+        
+        require!(
+            ctx.accounts.authority.key() == token_state.authority,
+            MyError::UnauthorizedAuthority
+        );
+        
+        token_state.pending_authority = Some(new_authority);
+        
+        msg!("Proposed {} as the new token authority", new_authority);
+        
+        Ok(())
+    }
     
-    /// CHECK: This is the token account that will receive the minted tokens
-    #[account(mut)]
-    pub token_account: AccountInfo<'info>,
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        let token_state = &mut ctx.accounts.token_state;
+        
+        require!(
+            token_state.pending_authority.is_some(),
+            MyError::NoPendingAuthority
+        );
+        require!(
+            Some(ctx.accounts.new_authority.key()) == token_state.pending_authority,
+            MyError::UnauthorizedPendingAuthority
+        );
+        
+        token_state.authority = ctx.accounts.new_authority.key();
+        token_state.pending_authority = None;
+        
+        msg!("Token authority transferred to {}", token_state.authority);
+        
+        Ok(())
+    }
     
-    /// CHECK: This is the token program ID
-    pub token_program: AccountInfo<'info>,
-}
+    /// Moves the mint authority. A wallet mint authority signs for itself;
+    /// a PDA mint authority is moved by the token authority. Capped mints
+    /// must keep the PDA, or renounce minting altogether.
+    pub fn set_mint_authority(
+        ctx: Context<SetMintAuthority>,
+        new_mint_authority: Pubkey,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.token_state.max_supply.is_none(),
+            MyError::MaxSupplyRequiresPdaMintAuthority
+        );
 
-#[derive(Accounts)]
-pub struct BurnTokens<'info> {
-    #[account(mut)]
-    pub owner: Signer<'info>,
-    
-    #[account(mut)]
-    pub token_state: Account<'info, TokenState>,
+        move_mint_authority(ctx.accounts, ctx.bumps.mint_authority, Some(new_mint_authority))?;
+        
+        ctx.accounts.token_state.mint_authority = Some(new_mint_authority);
+        
+        msg!("Mint authority set to {}", new_mint_authority);
+        
+        Ok(())
+    }
     
-    /// CHECK: This is the mint account
-    #[account(
-        mut,
-        constraint = token_state.mint == *mint.key
-    )]
-    pub mint: AccountInfo<'info>,
+    /// Permanently removes the mint authority, fixing the token supply.
+    pub fn renounce_mint_authority(ctx: Context<SetMintAuthority>) -> Result<()> {
+        move_mint_authority(ctx.accounts, ctx.bumps.mint_authority, None)?;
+        
+        ctx.accounts.token_state.mint_authority = None;
+        
+        msg!("Mint authority renounced; supply is now fixed");
+        
+        Ok(())
+    }
     
-    /// CHECK: This is the token account that the tokens will be burned from
-    #[account(mut)]
-    pub token_account: AccountInfo<'info>,
+    /// Moves the freeze authority, or removes it for good when `None`.
+    pub fn set_freeze_authority(
+        ctx: Context<SetFreezeAuthority>,
+        new_freeze_authority: Option<Pubkey>,
+    ) -> Result<()> {
+        require!(
+            Some(ctx.accounts.freeze_authority.key()) == ctx.accounts.token_state.freeze_authority,
+            MyError::UnauthorizedFreezeAuthority
+        );
+        
+        let cpi_accounts = SetAuthority {
+            current_authority: ctx.accounts.freeze_authority.to_account_info(),
+            account_or_mint: ctx.accounts.mint.to_account_info(),
+        };
+        
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        
+        token_interface::set_authority(cpi_ctx, AuthorityType::FreezeAccount, new_freeze_authority)?;
+        
+        ctx.accounts.token_state.freeze_authority = new_freeze_authority;
+        
+        match new_freeze_authority {
+            Some(new_freeze_authority) => msg!("Freeze authority set to {}", new_freeze_authority),
+            None => msg!("Freeze authority removed"),
+        }
+        
+        Ok(())
+    }
     
-    /// CHECK: This is the token program ID
-    pub token_program: AccountInfo<'info>,
-}
-
-#[derive(Accounts)]
-pub struct TransferTokens<'info> {
+    pub fn approve_tokens(
+        ctx: Context<ApproveTokens>,
+        amount: u64,
+    ) -> Result<()> {
+        msg!("Approving {} tokens for delegate", amount);
+        
+        let cpi_accounts = Approve {
+            to: ctx.accounts.token_account.to_account_info(),
+            delegate: ctx.accounts.delegate.clone(),
+            authority: ctx.accounts.owner.to_account_info(),
+        };
+        
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        
+        token_interface::approve(cpi_ctx, amount)?;
+        
+        msg!("Successfully approved {} tokens for delegate", amount);
+        
+        emit!(DelegateApproved {
+            mint: ctx.accounts.mint.key(),
+            owner: ctx.accounts.owner.key(),
+            token_account: ctx.accounts.token_account.key(),
+            delegate: ctx.accounts.delegate.key(),
+            amount,
+        });
+        
+        Ok(())
+    }
+    
+    pub fn revoke_delegate(ctx: Context<RevokeDelegate>) -> Result<()> {
+        let cpi_accounts = Revoke {
+            source: ctx.accounts.token_account.to_account_info(),
+            authority: ctx.accounts.owner.to_account_info(),
+        };
+        
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        
+        token_interface::revoke(cpi_ctx)?;
+        
+        msg!("Successfully revoked the delegate");
+        
+        emit!(DelegateRevoked {
+            mint: ctx.accounts.mint.key(),
+            owner: ctx.accounts.owner.key(),
+            token_account: ctx.accounts.token_account.key(),
+        });
+        
+        Ok(())
+    }
+    
+    /// Raises the delegate's allowance by `amount`. Delegating to a new
+    /// delegate starts from zero, replacing the previous one.
+    pub fn increase_allowance(
+        ctx: Context<AdjustAllowance>,
+        amount: u64,
+    ) -> Result<()> {
+        let token_account = &ctx.accounts.token_account;
+        let current_allowance = if token_account.delegate == Some(ctx.accounts.delegate.key()).into() {
+            token_account.delegated_amount
+        } else {
+            0
+        };
+        let new_allowance = current_allowance.checked_add(amount).ok_or(MyError::OverflowError)?;
+        
+        approve_checked(ctx.accounts, new_allowance)?;
+        
+        msg!("Delegate allowance increased to {}", new_allowance);
+        
+        emit!(DelegateApproved {
+            mint: ctx.accounts.mint.key(),
+            owner: ctx.accounts.owner.key(),
+            token_account: ctx.accounts.token_account.key(),
+            delegate: ctx.accounts.delegate.key(),
+            amount: new_allowance,
+        });
+        
+        Ok(())
+    }
+    
+    pub fn decrease_allowance(
+        ctx: Context<AdjustAllowance>,
+        amount: u64,
+    ) -> Result<()> {
+        let token_account = &ctx.accounts.token_account;
+        
+        require!(
+            token_account.delegate == Some(ctx.accounts.delegate.key()).into(),
+            MyError::DelegateMismatch
+        );
+        
+        let new_allowance = token_account.delegated_amount.checked_sub(amount).ok_or(MyError::UnderflowError)?;
+        
+        approve_checked(ctx.accounts, new_allowance)?;
+        
+        msg!("Delegate allowance decreased to {}", new_allowance);
+        
+        emit!(DelegateApproved {
+            mint: ctx.accounts.mint.key(),
+            owner: ctx.accounts.owner.key(),
+            token_account: ctx.accounts.token_account.key(),
+            delegate: ctx.accounts.delegate.key(),
+            amount: new_allowance,
+        });
+        
+        Ok(())
+    }
+}
+
+/// Sets the mint's MintTokens authority. When the mint is PDA-controlled the
+/// token authority must sign and the program signs for the PDA; otherwise the
+/// signer must be the wallet mint authority.
+fn move_mint_authority(accounts: &SetMintAuthority, bump: u8, new_mint_authority: Option<Pubkey>) -> Result<()> {
+    let token_state = &accounts.token_state;
+    let cpi_program = accounts.token_program.to_account_info();
+    
+    if token_state.mint_authority == Some(accounts.mint_authority.key()) {
+        require!(
+            accounts.authority.key() == token_state.authority,
+            MyError::UnauthorizedAuthority
+        );
+        
+        let mint_key = accounts.mint.key();
+        let seeds = &[b"mint_authority".as_ref(), mint_key.as_ref(), &[bump]];
+        let signer = &[&seeds[..]];
+        
+        let cpi_accounts = SetAuthority {
+            current_authority: accounts.mint_authority.to_account_info(),
+            account_or_mint: accounts.mint.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        
+        token_interface::set_authority(cpi_ctx, AuthorityType::MintTokens, new_mint_authority)?;
+    } else {
+        require!(
+            Some(accounts.authority.key()) == token_state.mint_authority,
+            MyError::UnauthorizedMintAuthority
+        );
+        
+        let cpi_accounts = SetAuthority {
+            current_authority: accounts.authority.to_account_info(),
+            account_or_mint: accounts.mint.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        
+        token_interface::set_authority(cpi_ctx, AuthorityType::MintTokens, new_mint_authority)?;
+    }
+    
+    Ok(())
+}
+
+/// Sets the delegate's allowance with Token-2022 `approve_checked`, which
+/// anchor-spl only wraps for the legacy token program.
+fn approve_checked(accounts: &AdjustAllowance, amount: u64) -> Result<()> {
+    let ix = spl_token_2022::instruction::approve_checked(
+        &accounts.token_program.key(),
+        &accounts.token_account.key(),
+        &accounts.mint.key(),
+        &accounts.delegate.key(),
+        &accounts.owner.key(),
+        &[],
+        amount,
+        accounts.mint.decimals,
+    )?;
+    
+    invoke(
+        &ix,
+        &[
+            accounts.token_account.to_account_info(),
+            accounts.mint.to_account_info(),
+            accounts.delegate.to_account_info(),
+            accounts.owner.to_account_info(),
+        ],
+    )?;
+    
+    Ok(())
+}
+
+#[account]
+#[derive(Default)]
+pub struct TokenState {
+    pub authority: Pubkey,    // 32 bytes
+    pub mint: Pubkey,         // 32 bytes
+    pub decimals: u8,         // 1 byte
+    pub total_supply: u64,    // 8 bytes
+    pub freeze_authority: Option<Pubkey>, // 33 bytes
+    pub pending_authority: Option<Pubkey>, // 33 bytes
+    pub mint_authority: Option<Pubkey>, // 33 bytes
+    pub max_supply: Option<u64>, // 9 bytes, hard cap on the mint supply, PDA-controlled mints only
+}
+
+impl TokenState {
+    pub const SIZE: usize = 32 + 32 + 1 + 8 + 33 + 33 + 33 + 9;
+}
+
+/// Program or wallet allowed to mint through the PDA mint authority.
+#[account]
+pub struct Minter {
+    pub token_state: Pubkey,  // 32 bytes
+    pub minter: Pubkey,       // 32 bytes
+    pub allowance: u64,       // 8 bytes, total the minter may mint
+    pub minted: u64,          // 8 bytes
+}
+
+impl Minter {
+    pub const SIZE: usize = 32 + 32 + 8 + 8;
+}
+
+/// Highest transfer fee Token-2022 accepts, in basis points (100%).
+pub const MAX_FEE_BASIS_POINTS: u16 = 10_000;
+
+/// Transfer fee configured on the mint through the TransferFeeConfig extension.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct TransferFeeArgs {
+    pub basis_points: u16,
+    pub maximum_fee: u64,
+}
+
+/// Additional key/value pair stored in the mint's token metadata.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct MetadataEntry {
+    pub key: String,
+    pub value: String,
+}
+
+/// Token metadata field targeted by `update_metadata`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub enum MetadataField {
+    Name,
+    Symbol,
+    Uri,
+    Key(String),
+}
+
+impl From<MetadataField> for Field {
+    fn from(field: MetadataField) -> Self {
+        match field {
+            MetadataField::Name => Field::Name,
+            MetadataField::Symbol => Field::Symbol,
+            MetadataField::Uri => Field::Uri,
+            MetadataField::Key(key) => Field::Key(key),
+        }
+    }
+}
+
+#[derive(Accounts)]
+pub struct InitializeToken<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + TokenState::SIZE
+    )]
+    pub token_state: Account<'info, TokenState>,
+    
+    /// The mint account, created and sized for its extensions in the instruction
+    #[account(mut)]
+    pub mint: Signer<'info>,
+    
+    /// CHECK: This is the token account that is initialized in the instruction
+    #[account(mut)]
+    pub token_account: AccountInfo<'info>,
+    
+    /// CHECK: PDA set as the mint's metadata, metadata-pointer, transfer-fee and transfer-hook authority
+    #[account(
+        seeds = [b"extension_authority", mint.key().as_ref()],
+        bump
+    )]
+    pub extension_authority: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token2022>,
+    
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateMetadata<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub token_state: Account<'info, TokenState>,
+    
+    /// The mint account holding the token metadata
+    #[account(
+        mut,
+        address = token_state.mint @ MyError::InvalidMint,
+        mint::token_program = token_program
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    /// CHECK: PDA holding the metadata update authority
+    #[account(
+        seeds = [b"extension_authority", mint.key().as_ref()],
+        bump
+    )]
+    pub extension_authority: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MintTokens<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(mut)]
+    pub token_state: Account<'info, TokenState>,
+    
+    #[account(
+        mut,
+        address = token_state.mint @ MyError::InvalidMint,
+        mint::token_program = token_program
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    /// The token account that will receive the minted tokens
+    #[account(
+        mut,
+        token::mint = mint,
+        token::token_program = token_program
+    )]
+    pub token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+    
+    /// CHECK: PDA mint authority, only passed when the mint is PDA-controlled
+    #[account(
+        seeds = [b"mint_authority", mint.key().as_ref()],
+        bump
+    )]
+    pub mint_authority: Option<UncheckedAccount<'info>>,
+    
+    #[account(
+        mut,
+        seeds = [b"minter", token_state.key().as_ref(), authority.key().as_ref()],
+        bump
+    )]
+    pub minter: Option<Account<'info, Minter>>,
+}
+
+#[derive(Accounts)]
+pub struct DecreaseMaxSupply<'info> {
+    pub authority: Signer<'info>,
+    
+    #[account(mut)]
+    pub token_state: Account<'info, TokenState>,
+    
+    #[account(address = token_state.mint @ MyError::InvalidMint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+}
+
+#[derive(Accounts)]
+#[instruction(minter: Pubkey)]
+pub struct SetMinter<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub token_state: Account<'info, TokenState>,
+    
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + Minter::SIZE,
+        seeds = [b"minter", token_state.key().as_ref(), minter.as_ref()],
+        bump
+    )]
+    pub minter_account: Account<'info, Minter>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveMinter<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub token_state: Account<'info, TokenState>,
+    
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"minter", token_state.key().as_ref(), minter_account.minter.as_ref()],
+        bump
+    )]
+    pub minter_account: Account<'info, Minter>,
+}
+
+#[derive(Accounts)]
+pub struct BurnTokens<'info> {
     #[account(mut)]
     pub owner: Signer<'info>,
     
-    /// CHECK: This is the sender's token account
     #[account(mut)]
-    pub from: AccountInfo<'info>,
+    pub token_state: Account<'info, TokenState>,
+    
+    #[account(
+        mut,
+        address = token_state.mint @ MyError::InvalidMint,
+        mint::token_program = token_program
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    /// The token account that the tokens will be burned from
+    #[account(
+        mut,
+        token::mint = mint,
+        token::token_program = token_program
+    )]
+    pub token_account: InterfaceAccount<'info, TokenAccount>,
     
-    /// CHECK: This is the recipient's token account
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct TransferTokens<'info> {
     #[account(mut)]
-    pub to: AccountInfo<'info>,
+    pub owner: Signer<'info>,
+    
+    /// The sender's token account
+    #[account(
+        mut,
+        token::mint = mint,
+        token::token_program = token_program
+    )]
+    pub from: InterfaceAccount<'info, TokenAccount>,
+    
+    /// The recipient's token account
+    #[account(
+        mut,
+        token::mint = mint,
+        token::token_program = token_program
+    )]
+    pub to: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        address = token_state.mint @ MyError::InvalidMint,
+        mint::token_program = token_program
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
     
-    /// CHECK: This is the mint account
-    pub mint: AccountInfo<'info>,
+    pub token_state: Account<'info, TokenState>,
     
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct HarvestWithheldFees<'info> {
     pub token_state: Account<'info, TokenState>,
     
-    /// CHECK: This is the token program ID
-    pub token_program: AccountInfo<'info>,
+    /// The mint account that collects the harvested fees
+    #[account(
+        mut,
+        address = token_state.mint @ MyError::InvalidMint,
+        mint::token_program = token_program
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawWithheldFees<'info> {
+    pub authority: Signer<'info>,
+    
+    pub token_state: Account<'info, TokenState>,
+    
+    /// The mint account holding the harvested fees
+    #[account(
+        mut,
+        address = token_state.mint @ MyError::InvalidMint,
+        mint::token_program = token_program
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    /// The treasury token account that receives the fees
+    #[account(
+        mut,
+        token::mint = mint,
+        token::token_program = token_program
+    )]
+    pub destination: InterfaceAccount<'info, TokenAccount>,
+    
+    /// CHECK: PDA holding the withdraw-withheld authority
+    #[account(
+        seeds = [b"extension_authority", mint.key().as_ref()],
+        bump
+    )]
+    pub extension_authority: UncheckedAccount<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct SetTransferFee<'info> {
+    pub authority: Signer<'info>,
+    
+    pub token_state: Account<'info, TokenState>,
+    
+    #[account(
+        mut,
+        address = token_state.mint @ MyError::InvalidMint,
+        mint::token_program = token_program
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    /// CHECK: PDA holding the transfer fee config authority
+    #[account(
+        seeds = [b"extension_authority", mint.key().as_ref()],
+        bump
+    )]
+    pub extension_authority: UncheckedAccount<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct FreezeTokenAccount<'info> {
+    pub freeze_authority: Signer<'info>,
+    
+    pub token_state: Account<'info, TokenState>,
+    
+    #[account(
+        address = token_state.mint @ MyError::InvalidMint,
+        mint::token_program = token_program
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    /// The token account to freeze
+    #[account(
+        mut,
+        token::mint = mint,
+        token::token_program = token_program
+    )]
+    pub token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ThawTokenAccount<'info> {
+    pub freeze_authority: Signer<'info>,
+    
+    pub token_state: Account<'info, TokenState>,
+    
+    #[account(
+        address = token_state.mint @ MyError::InvalidMint,
+        mint::token_program = token_program
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    /// The token account to thaw
+    #[account(
+        mut,
+        token::mint = mint,
+        token::token_program = token_program
+    )]
+    pub token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeAuthority<'info> {
+    pub authority: Signer<'info>,
+    
+    #[account(mut)]
+    pub token_state: Account<'info, TokenState>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    pub new_authority: Signer<'info>,
+    
+    #[account(mut)]
+    pub token_state: Account<'info, TokenState>,
+}
+
+#[derive(Accounts)]
+pub struct SetMintAuthority<'info> {
+    /// The wallet mint authority, or the token authority when the mint is PDA-controlled
+    pub authority: Signer<'info>,
+    
+    #[account(mut)]
+    pub token_state: Account<'info, TokenState>,
+    
+    #[account(
+        mut,
+        address = token_state.mint @ MyError::InvalidMint,
+        mint::token_program = token_program
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    /// CHECK: PDA mint authority, signed for by the program when it holds the mint authority
+    #[account(
+        seeds = [b"mint_authority", mint.key().as_ref()],
+        bump
+    )]
+    pub mint_authority: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct SetFreezeAuthority<'info> {
+    pub freeze_authority: Signer<'info>,
+    
+    #[account(mut)]
+    pub token_state: Account<'info, TokenState>,
+    
+    #[account(
+        mut,
+        address = token_state.mint @ MyError::InvalidMint,
+        mint::token_program = token_program
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 #[derive(Accounts)]
@@ -309,25 +1501,159 @@ pub struct ApproveTokens<'info> {
     #[account(mut)]
     pub owner: Signer<'info>,
     
-    /// CHECK: This is the token account
-    #[account(mut)]
-    pub token_account: AccountInfo<'info>,
+    pub token_state: Account<'info, TokenState>,
+    
+    #[account(
+        address = token_state.mint @ MyError::InvalidMint,
+        mint::token_program = token_program
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    /// The owner's token account
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = owner,
+        token::token_program = token_program
+    )]
+    pub token_account: InterfaceAccount<'info, TokenAccount>,
     
     /// CHECK: This is the delegate that will be approved to spend tokens
     pub delegate: AccountInfo<'info>,
     
-    /// CHECK: This is the token program ID
-    pub token_program: AccountInfo<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeDelegate<'info> {
+    pub owner: Signer<'info>,
+    
+    pub token_state: Account<'info, TokenState>,
+    
+    #[account(
+        address = token_state.mint @ MyError::InvalidMint,
+        mint::token_program = token_program
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    /// The owner's token account
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = owner,
+        token::token_program = token_program
+    )]
+    pub token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct AdjustAllowance<'info> {
+    pub owner: Signer<'info>,
+    
+    pub token_state: Account<'info, TokenState>,
+
+    /// The owner's token account
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = owner,
+        token::token_program = token_program
+    )]
+    pub token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        address = token_state.mint @ MyError::InvalidMint,
+        mint::token_program = token_program
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    /// CHECK: This is the delegate whose allowance is adjusted
+    pub delegate: AccountInfo<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[event]
+pub struct TokensMinted {
+    pub mint: Pubkey,
+    pub authority: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub total_supply: u64,
+}
+
+#[event]
+pub struct TokensBurned {
+    pub mint: Pubkey,
+    pub authority: Pubkey,
+    pub source: Pubkey,
+    pub amount: u64,
+    pub total_supply: u64,
+}
+
+#[event]
+pub struct TokensTransferred {
+    pub mint: Pubkey,
+    pub authority: Pubkey,
+    pub source: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub fee: u64,             // Transfer fee withheld, 0 for mints without one
+}
+
+/// Emitted whenever a delegate's allowance is set; `amount` is the new allowance.
+#[event]
+pub struct DelegateApproved {
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub token_account: Pubkey,
+    pub delegate: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct DelegateRevoked {
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub token_account: Pubkey,
 }
 
 #[error_code]
 pub enum MyError {
     #[msg("Only the mint authority can mint new tokens")]
     UnauthorizedMintAuthority,
-    #[msg("Only the owner can burn tokens")]
+    #[msg("Only the token account owner or its delegate can burn tokens")]
     UnauthorizedBurnAuthority,
     #[msg("Arithmetic overflow")]
     OverflowError,
     #[msg("Arithmetic underflow")]
     UnderflowError,
+    #[msg("Only the token authority can update metadata")]
+    UnauthorizedMetadataAuthority,
+    #[msg("Transfer fee basis points must not exceed 10000")]
+    InvalidTransferFee,
+    #[msg("Only the freeze authority can freeze or thaw token accounts")]
+    UnauthorizedFreezeAuthority,
+    #[msg("Only the token authority can propose a new authority")]
+    UnauthorizedAuthority,
+    #[msg("No authority transfer has been proposed")]
+    NoPendingAuthority,
+    #[msg("Only the proposed authority can accept the transfer")]
+    UnauthorizedPendingAuthority,
+    #[msg("Mint amount exceeds the minter's allowance")]
+    MinterAllowanceExceeded,
+    #[msg("Minting would exceed the maximum supply")]
+    MaxSupplyExceeded,
+    #[msg("Maximum supply can only decrease, and not below the current supply")]
+    InvalidMaxSupply,
+    #[msg("Mint does not match this token")]
+    InvalidMint,
+    #[msg("Token account is not delegated to this delegate")]
+    DelegateMismatch,
+    #[msg("A maximum supply requires the program PDA to hold the mint authority")]
+    MaxSupplyRequiresPdaMintAuthority,
+    #[msg("Mints with frozen default accounts need a freeze authority")]
+    FreezeAuthorityRequired,
 }
@@ -0,0 +1,158 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount};
+use anchor_spl::token_2022::spl_token_2022::extension::{BaseStateWithExtensions, StateWithExtensions};
+use anchor_spl::token_2022::spl_token_2022::extension::transfer_hook::TransferHookAccount;
+use anchor_spl::token_2022::spl_token_2022::state::Account as SplTokenAccount;
+use spl_tlv_account_resolution::account::ExtraAccountMeta;
+use spl_tlv_account_resolution::seeds::Seed;
+use spl_tlv_account_resolution::state::ExtraAccountMetaList;
+use spl_transfer_hook_interface::instruction::ExecuteInstruction;
+use presale::LockupRecord;
+
+declare_id!("CCrydRS2rqYsbJHp1fUaX8SXFxaCcXA2WbeiKYZioPv3");
+
+#[program]
+pub mod transfer_hook {
+    use super::*;
+
+    /// Creates the extra-account-metas PDA Token-2022 reads to resolve the
+    /// accounts this hook needs on every transfer of `mint`.
+    pub fn initialize_extra_account_meta_list(
+        ctx: Context<InitializeExtraAccountMetaList>,
+    ) -> Result<()> {
+        let account_metas = extra_account_metas()?;
+        let mut data = ctx.accounts.extra_account_meta_list.try_borrow_mut_data()?;
+
+        ExtraAccountMetaList::init::<ExecuteInstruction>(&mut data, &account_metas)?;
+
+        msg!("Extra account metas initialized for mint {}", ctx.accounts.mint.key());
+
+        Ok(())
+    }
+
+    /// Called by Token-2022 after every transfer of a hooked mint. Rejects the
+    /// transfer if it leaves the source token account below its locked presale
+    /// balance.
+    ///
+    /// Lockups are recorded per token account, so only the account presale
+    /// claims were paid into is restricted; other accounts of the same wallet
+    /// transfer freely. Each presale's tranche unlocks on its own schedule.
+    #[interface(spl_transfer_hook_interface::execute)]
+    pub fn transfer_hook(ctx: Context<TransferHook>, amount: u64) -> Result<()> {
+        // Only Token-2022 may run the hook, which it marks on the source account
+        let source_info = ctx.accounts.source_token.to_account_info();
+        let source_data = source_info.try_borrow_data()?;
+        let source_account = StateWithExtensions::<SplTokenAccount>::unpack(&source_data)?;
+        let transfer_hook_account = source_account.get_extension::<TransferHookAccount>()?;
+        require!(
+            bool::from(transfer_hook_account.transferring),
+            TransferHookError::NotTransferring
+        );
+
+        // Token accounts that never received presale claims have no lockup record
+        let lockup_info = ctx.accounts.lockup.to_account_info();
+        if lockup_info.owner != &presale::ID || lockup_info.data_is_empty() {
+            return Ok(());
+        }
+
+        let lockup = LockupRecord::try_deserialize(&mut &lockup_info.try_borrow_data()?[..])?;
+        let locked_amount = lockup.locked_amount(Clock::get()?.unix_timestamp);
+
+        // The source balance has already been debited by the time the hook runs
+        require!(
+            ctx.accounts.source_token.amount >= locked_amount,
+            TransferHookError::TokensLocked
+        );
+
+        msg!("Transfer of {} tokens allowed", amount);
+
+        Ok(())
+    }
+}
+
+/// Accounts appended to each transfer, after the extra-account-metas PDA at
+/// index 4: the presale program and the source token account's lockup record.
+fn extra_account_metas() -> Result<Vec<ExtraAccountMeta>> {
+    Ok(vec![
+        // index 5: presale program
+        ExtraAccountMeta::new_with_pubkey(&presale::ID, false, false)?,
+        // index 6: lockup record, [b"lockup", mint, source token account] under the presale program
+        ExtraAccountMeta::new_external_pda_with_seeds(
+            5,
+            &[
+                Seed::Literal {
+                    bytes: b"lockup".to_vec(),
+                },
+                Seed::AccountKey { index: 1 },
+                Seed::AccountKey { index: 0 },
+            ],
+            false,
+            false,
+        )?,
+    ])
+}
+
+#[derive(Accounts)]
+pub struct InitializeExtraAccountMetaList<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: This is the extra-account-metas PDA, written in the instruction
+    #[account(
+        init,
+        payer = payer,
+        space = ExtraAccountMetaList::size_of(extra_account_metas()?.len())?,
+        seeds = [b"extra-account-metas", mint.key().as_ref()],
+        bump
+    )]
+    pub extra_account_meta_list: UncheckedAccount<'info>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct TransferHook<'info> {
+    #[account(
+        token::mint = mint
+    )]
+    pub source_token: InterfaceAccount<'info, TokenAccount>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        token::mint = mint
+    )]
+    pub destination_token: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: Source owner or delegate, already verified by Token-2022
+    pub owner: UncheckedAccount<'info>,
+
+    /// CHECK: This is the extra-account-metas PDA for the mint
+    #[account(
+        seeds = [b"extra-account-metas", mint.key().as_ref()],
+        bump
+    )]
+    pub extra_account_meta_list: UncheckedAccount<'info>,
+
+    /// CHECK: This is the presale program that owns lockup records
+    #[account(address = presale::ID)]
+    pub presale_program: UncheckedAccount<'info>,
+
+    /// CHECK: Lockup record of the source token account; may not exist
+    #[account(
+        seeds = [b"lockup", mint.key().as_ref(), source_token.key().as_ref()],
+        seeds::program = presale_program.key(),
+        bump
+    )]
+    pub lockup: UncheckedAccount<'info>,
+}
+
+#[error_code]
+pub enum TransferHookError {
+    #[msg("Transfer would move tokens that are still locked from the presale")]
+    TokensLocked,
+    #[msg("The transfer hook can only be invoked during a transfer")]
+    NotTransferring,
+}